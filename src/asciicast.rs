@@ -1,12 +1,17 @@
+mod raw;
+mod script;
+mod terminalizer;
+mod ttyrec;
 mod v1;
 mod v2;
 mod v3;
 
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Cursor, Read, Write};
 
 use anyhow::{anyhow, Result};
 
 use crate::theme::Theme;
+use crate::RawTiming;
 
 pub struct Asciicast<'a> {
     pub header: Header,
@@ -18,12 +23,17 @@ pub struct Header {
     pub term_rows: u16,
     pub term_theme: Option<Theme>,
     pub idle_time_limit: Option<f64>,
+    pub title: Option<String>,
+    pub command: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Event {
     Output(f64, String),
+    Input(f64, String),
     Marker(f64, String),
+    Resize(f64, usize, usize),
+    Exit(f64, i32),
 }
 
 pub type OutputEvent = (f64, String);
@@ -35,11 +45,25 @@ impl Default for Header {
             term_rows: 24,
             term_theme: None,
             idle_time_limit: None,
+            title: None,
+            command: None,
         }
     }
 }
 
-pub fn open<'a, R: BufRead + 'a>(reader: R) -> Result<Asciicast<'a>> {
+pub fn open<'a, R: BufRead + 'a>(mut reader: R) -> Result<Asciicast<'a>> {
+    let mut head = Vec::with_capacity(ttyrec::HEADER_LEN);
+    reader
+        .by_ref()
+        .take(ttyrec::HEADER_LEN as u64)
+        .read_to_end(&mut head)?;
+
+    let reader = Cursor::new(head.clone()).chain(reader);
+
+    if ttyrec::detect(&head) {
+        return ttyrec::load(reader);
+    }
+
     let mut lines = reader.lines();
     let first_line = lines.next().ok_or(anyhow!("empty file"))??;
 
@@ -48,10 +72,232 @@ pub fn open<'a, R: BufRead + 'a>(reader: R) -> Result<Asciicast<'a>> {
     } else if let Ok(parser) = v2::open(&first_line) {
         Ok(parser.parse(lines))
     } else {
-        let json = std::iter::once(Ok(first_line))
+        let text = std::iter::once(Ok(first_line))
             .chain(lines)
-            .collect::<io::Result<String>>()?;
+            .collect::<io::Result<Vec<String>>>()?
+            .join("\n");
+
+        v1::load(&text)
+            .or_else(|_| terminalizer::load(&text))
+            .map_err(|_| anyhow!("not a v1, v2, v3 asciicast, ttyrec or terminalizer file"))
+    }
+}
+
+/// Opens a typescript recorded with script(1), timed by its timing file.
+/// The terminal size comes from the typescript header, or `default_size`
+/// when it's not recorded there.
+pub fn open_script<'a, R: BufRead + 'a, T: BufRead>(
+    typescript: R,
+    timing: T,
+    default_size: (u16, u16),
+) -> Result<Asciicast<'a>> {
+    script::open(typescript, timing, default_size)
+}
+
+/// Opens raw terminal output, which has no timing of its own, spread over
+/// time according to `timing`.
+pub fn open_raw<R: Read>(reader: R, timing: RawTiming, delay: f64) -> Result<Asciicast<'static>> {
+    raw::load(reader, timing, delay)
+}
+
+/// Turns raw terminal output, which may have multibyte characters split
+/// between chunks, into strings.
+#[derive(Default)]
+struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut output = String::new();
+
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(s) => {
+                    output.push_str(s);
+                    self.pending.clear();
+
+                    return output;
+                }
+
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    output.push_str(std::str::from_utf8(&self.pending[..valid]).unwrap());
+
+                    match e.error_len() {
+                        Some(len) => {
+                            output.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid + len);
+                        }
+
+                        // incomplete sequence at the end, wait for the rest of it
+                        None => {
+                            self.pending.drain(..valid);
+
+                            return output;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns what's left of an incomplete sequence at the end of the input.
+    fn finish(self) -> String {
+        String::from_utf8_lossy(&self.pending).into_owned()
+    }
+}
+
+/// Guesses the terminal size for formats which don't store it, from the
+/// cursor positions (CUP) and window size requests (XTWINOPS 8) found in the
+/// output. Falls back to 80 columns and 24 rows when there's nothing to go by.
+fn infer_size(events: &[Event]) -> (u16, u16) {
+    let mut cols = None;
+    let mut rows = None;
+    let grow = |size: &mut Option<u16>, n: u16| {
+        if n > 0 {
+            *size = Some(size.map_or(n, |s| s.max(n)));
+        }
+    };
+
+    for event in events {
+        let Event::Output(_, data) = event else {
+            continue;
+        };
+
+        let mut rest = data.as_str();
+
+        while let Some(i) = rest.find("\x1b[") {
+            rest = &rest[i + 2..];
+
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == ';'))
+                .unwrap_or(rest.len());
+
+            let params: Vec<u16> = rest[..end]
+                .split(';')
+                .map(|p| p.parse().unwrap_or(1))
+                .collect();
+
+            match (rest[end..].chars().next(), params.as_slice()) {
+                (Some('H' | 'f'), [row, col]) | (Some('t'), [8, row, col]) => {
+                    grow(&mut cols, *col);
+                    grow(&mut rows, *row);
+                }
+
+                _ => (),
+            }
+        }
+    }
+
+    (cols.unwrap_or(80), rows.unwrap_or(24))
+}
+
+/// Parses "{cols}x{rows}" data of a resize event.
+fn parse_size(data: &str) -> Result<(usize, usize)> {
+    data.split_once('x')
+        .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)))
+        .filter(|(cols, rows)| *cols > 0 && *rows > 0)
+        .ok_or_else(|| anyhow!("invalid terminal size in resize event: {data}"))
+}
+
+/// Writes events as an asciicast v3 file.
+pub fn write<W: Write>(
+    output: W,
+    header: &Header,
+    events: impl Iterator<Item = Result<Event>>,
+) -> Result<()> {
+    v3::write(output, header, events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Header};
+    use crate::theme::Theme;
+
+    #[test]
+    fn write() {
+        let header = Header {
+            term_cols: 20,
+            term_rows: 5,
+            term_theme: Some(
+                "000000,ffffff,010101,020202,030303,040404,050505,060606,070707,080808"
+                    .parse::<Theme>()
+                    .unwrap(),
+            ),
+            idle_time_limit: None,
+            title: Some("demo".to_owned()),
+            command: Some("bash -l".to_owned()),
+        };
+
+        let events = vec![
+            Event::Output(0.0, "foo".to_owned()),
+            Event::Marker(0.5, "m1".to_owned()),
+            Event::Input(1.0, "\r".to_owned()),
+            Event::Output(1.25, "\"bar\"\r\n".to_owned()),
+            Event::Resize(2.0, 30, 10),
+            Event::Output(3.000001, "!".to_owned()),
+            Event::Exit(3.5, 1),
+        ];
+
+        let mut cast = Vec::new();
+        super::write(&mut cast, &header, events.into_iter().map(Ok)).unwrap();
+        let cast = String::from_utf8(cast).unwrap();
+        let lines: Vec<&str> = cast.lines().collect();
+
+        assert_eq!(lines[1], r#"[0.000000, "o", "foo"]"#);
+        assert_eq!(lines[2], r#"[0.500000, "m", "m1"]"#);
+        assert_eq!(lines[3], r#"[0.500000, "i", "\r"]"#);
+        assert_eq!(lines[4], r#"[0.250000, "o", "\"bar\"\r\n"]"#);
+        assert_eq!(lines[5], r#"[0.750000, "r", "30x10"]"#);
+        assert_eq!(lines[6], r#"[1.000001, "o", "!"]"#);
+        assert_eq!(lines[7], r#"[0.499999, "x", "1"]"#);
+
+        let asciicast = super::open(cast.as_bytes()).unwrap();
+
+        assert_eq!(asciicast.header.term_cols, 20);
+        assert_eq!(asciicast.header.term_rows, 5);
+        assert_eq!(asciicast.header.term_theme, header.term_theme);
+        assert_eq!(asciicast.header.title, header.title);
+        assert_eq!(asciicast.header.command, header.command);
+
+        let events: Vec<Event> = asciicast.events.map(Result::unwrap).collect();
+
+        assert_eq!(events[1], Event::Marker(0.5, "m1".to_owned()));
+        assert_eq!(events[2], Event::Input(1.0, "\r".to_owned()));
+        assert_eq!(events[3], Event::Output(1.25, "\"bar\"\r\n".to_owned()));
+        assert_eq!(events[4], Event::Resize(2.0, 30, 10));
+        assert_eq!(events[6], Event::Exit(3.5, 1));
+    }
+
+    #[test]
+    fn skip_invalid_events() {
+        let v2 = "{\"version\": 2, \"width\": 20, \"height\": 5}\n\
+                  [1.0, \"r\", \"80\"]\n\
+                  [2.0, \"o\", \"foo\"]\n";
+
+        let v3 = "{\"version\": 3, \"term\": {\"cols\": 20, \"rows\": 5}}\n\
+                  [1.0, \"r\", \"0x24\"]\n\
+                  [1.0, \"o\", \"foo\"]\n\
+                  [0.5, \"x\", \"killed\"]\n";
+
+        for cast in [v2, v3] {
+            let events: Vec<Event> = super::open(cast.as_bytes())
+                .unwrap()
+                .events
+                .map(Result::unwrap)
+                .collect();
+
+            assert_eq!(events, vec![Event::Output(2.0, "foo".to_owned())]);
+        }
+    }
 
-        v1::load(json).map_err(|_| anyhow!("not a v1, v2, v3 asciicast file"))
+    #[test]
+    fn parse_size() {
+        assert_eq!(super::parse_size("80x24").unwrap(), (80, 24));
+        assert!(super::parse_size("80").is_err());
+        assert!(super::parse_size("0x24").is_err());
+        assert!(super::parse_size("axb").is_err());
     }
 }
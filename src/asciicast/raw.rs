@@ -0,0 +1,181 @@
+use std::io::Read;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use anyhow::Result;
+
+use super::{infer_size, Asciicast, Event, Header};
+use crate::RawTiming;
+
+const CLEARS: [&str; 2] = ["\x1b[2J", "\x1bc"];
+const HOME: &str = "\x1b[H";
+
+/// Reads terminal output with no timing information, e.g. a log with color
+/// codes, and spreads it over time in lines, characters or screens, each
+/// one `delay` seconds after the previous.
+pub fn load<R: Read>(mut reader: R, timing: RawTiming, delay: f64) -> Result<Asciicast<'static>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    // files don't go through the tty, which turns "\n" into "\r\n"
+    let text = String::from_utf8_lossy(&data)
+        .replace("\r\n", "\n")
+        .replace('\n', "\r\n");
+
+    let chunks = match timing {
+        RawTiming::Lines => text.split_inclusive('\n').collect(),
+        RawTiming::Chars => chars(&text),
+        RawTiming::Screens => screens(&text),
+    };
+
+    let events: Vec<Event> = chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| Event::Output(i as f64 * delay, chunk.to_owned()))
+        .collect();
+
+    let (term_cols, term_rows) = infer_size(&events);
+
+    let header = Header {
+        term_cols,
+        term_rows,
+        ..Default::default()
+    };
+
+    Ok(Asciicast {
+        header,
+        events: Box::new(events.into_iter().map(Ok)),
+    })
+}
+
+/// Splits text into printable characters, each one together with the
+/// control characters and escape sequences preceding it.
+fn chars(text: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        if ch == '\x1b' {
+            skip_escape_sequence(&mut chars);
+        } else if ch == '\n' || !ch.is_control() {
+            let end = i + ch.len_utf8();
+            chunks.push(&text[start..end]);
+            start = end;
+        }
+    }
+
+    if start < text.len() {
+        chunks.push(&text[start..]);
+    }
+
+    chunks
+}
+
+fn skip_escape_sequence(chars: &mut Peekable<CharIndices>) {
+    match chars.next() {
+        // CSI, up to the final byte
+        Some((_, '[')) => while chars.next_if(|(_, c)| !('@'..='~').contains(c)).is_some() {},
+
+        // OSC, up to BEL or ST
+        Some((_, ']')) => {
+            while let Some((_, c)) = chars.next() {
+                if c == '\x07' || (c == '\x1b' && chars.next_if(|(_, c)| *c == '\\').is_some()) {
+                    return;
+                }
+            }
+
+            return;
+        }
+
+        // intermediate bytes of e.g. charset designation
+        Some((_, ' '..='/')) => while chars.next_if(|(_, c)| (' '..='/').contains(c)).is_some() {},
+
+        _ => return,
+    }
+
+    chars.next();
+}
+
+/// Splits text at clear screen sequences (including a preceding cursor
+/// home), so that each chunk draws one screen.
+fn screens(text: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = CLEARS
+        .iter()
+        .flat_map(|clear| text.match_indices(clear))
+        .map(|(i, _)| {
+            if text[..i].ends_with(HOME) {
+                i - HOME.len()
+            } else {
+                i
+            }
+        })
+        .collect();
+
+    starts.push(0);
+    starts.push(text.len());
+    starts.sort_unstable();
+    starts.dedup();
+
+    starts
+        .windows(2)
+        .map(|w| &text[w[0]..w[1]])
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asciicast::Event;
+    use crate::RawTiming;
+
+    fn load(text: &str, timing: RawTiming) -> Vec<Event> {
+        super::load(text.as_bytes(), timing, 0.5)
+            .unwrap()
+            .events
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            load("foo\nbar\r\nbaz", RawTiming::Lines),
+            [
+                Event::Output(0.0, "foo\r\n".to_owned()),
+                Event::Output(0.5, "bar\r\n".to_owned()),
+                Event::Output(1.0, "baz".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn chars() {
+        let chunks = super::chars("a\x1b[1;31mż\x1b]0;title\x07\x1b(B\x1b7 \r\n\x1b[0m");
+
+        assert_eq!(
+            chunks,
+            [
+                "a",
+                "\x1b[1;31mż",
+                "\x1b]0;title\x07\x1b(B\x1b7 ",
+                "\r\n",
+                "\x1b[0m"
+            ]
+        );
+    }
+
+    #[test]
+    fn screens() {
+        let chunks = super::screens("\x1b[H\x1b[2Jfoo\x1b[H\x1b[2Jbar\x1bcbaz");
+
+        assert_eq!(chunks, ["\x1b[H\x1b[2Jfoo", "\x1b[H\x1b[2Jbar", "\x1bcbaz"]);
+    }
+
+    #[test]
+    fn size() {
+        let cast = super::load("\x1b[30;100Hfoo".as_bytes(), RawTiming::Lines, 1.0).unwrap();
+
+        assert_eq!((cast.header.term_cols, cast.header.term_rows), (100, 30));
+    }
+}
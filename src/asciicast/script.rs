@@ -0,0 +1,269 @@
+use std::io::{BufRead, Cursor, Read};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::{Asciicast, Event, Header, Utf8Decoder};
+
+const HEADER_PREFIX: &[u8] = b"Script started on ";
+
+enum Entry {
+    Output(f64, usize),
+    Input(f64, usize),
+    Resize(f64, usize, usize),
+    Exit(f64, i32),
+    Info(String, String),
+}
+
+/// Reads a typescript written by script(1) together with its timing file,
+/// in either the classic (`delay bytes`) or the advanced (`-T`, `type delay
+/// data`) format. Input entries are read from the typescript as well, which
+/// is where `script --log-io` puts them.
+pub fn open<'a, R: BufRead + 'a, T: BufRead>(
+    mut typescript: R,
+    timing: T,
+    default_size: (u16, u16),
+) -> Result<Asciicast<'a>> {
+    let entries = timing
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .scan(0.0, |time, (i, line)| {
+            Some(
+                line.map_err(Into::into)
+                    .and_then(|line| parse_entry(&line, time))
+                    .with_context(|| format!("invalid timing file line {}", i + 1)),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut fields = Vec::new();
+    let mut first_line = Vec::new();
+    typescript.read_until(b'\n', &mut first_line)?;
+
+    // the header line is not covered by the timing file, while recordings
+    // made with --quiet start with the output right away
+    if first_line.starts_with(HEADER_PREFIX) {
+        fields = header_fields(&String::from_utf8_lossy(&first_line));
+        first_line.clear();
+    }
+
+    let mut typescript = Cursor::new(first_line).chain(typescript);
+
+    for entry in &entries {
+        if let Entry::Info(key, value) = entry {
+            fields.push((key.clone(), value.clone()));
+        }
+    }
+
+    let field = |name: &str| {
+        fields
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let size = |name: &str| field(name).and_then(|v| v.parse().ok()).filter(|v| *v > 0);
+
+    let header = Header {
+        term_cols: size("COLUMNS").unwrap_or(default_size.0),
+        term_rows: size("LINES").unwrap_or(default_size.1),
+        command: field("COMMAND").map(str::to_owned),
+        ..Default::default()
+    };
+
+    let mut output = Utf8Decoder::default();
+    let mut input = Utf8Decoder::default();
+
+    let events = entries.into_iter().filter_map(move |entry| {
+        let mut read = |len| {
+            let mut data = Vec::with_capacity(len);
+
+            typescript
+                .by_ref()
+                .take(len as u64)
+                .read_to_end(&mut data)
+                .map(|_| data)
+        };
+
+        match entry {
+            Entry::Output(time, len) => Some(
+                read(len)
+                    .map(|data| Event::Output(time, output.decode(&data)))
+                    .map_err(Into::into),
+            ),
+
+            Entry::Input(time, len) => Some(
+                read(len)
+                    .map(|data| Event::Input(time, input.decode(&data)))
+                    .map_err(Into::into),
+            ),
+
+            Entry::Resize(time, cols, rows) => Some(Ok(Event::Resize(time, cols, rows))),
+            Entry::Exit(time, status) => Some(Ok(Event::Exit(time, status))),
+            Entry::Info(..) => None,
+        }
+    });
+
+    Ok(Asciicast {
+        header,
+        events: Box::new(events),
+    })
+}
+
+/// Parses a timing file line, advancing the time by its delay.
+fn parse_entry(line: &str, time: &mut f64) -> Result<Entry> {
+    let mut parts = line.trim().splitn(3, ' ');
+    let first = parts.next().unwrap_or_default();
+
+    // classic format: "delay bytes"
+    if let Ok(delay) = first.parse::<f64>() {
+        *time += delay;
+        let len = parts.next().unwrap_or_default().parse()?;
+
+        return Ok(Entry::Output(*time, len));
+    }
+
+    let delay: f64 = parts.next().unwrap_or_default().parse()?;
+    let data = parts.next().unwrap_or_default();
+    *time += delay;
+
+    match first {
+        "O" => Ok(Entry::Output(*time, data.parse()?)),
+        "I" => Ok(Entry::Input(*time, data.parse()?)),
+
+        "H" => {
+            let (key, value) = data.split_once(' ').unwrap_or((data, ""));
+
+            match (key, value.parse()) {
+                ("EXIT_CODE", Ok(status)) => Ok(Entry::Exit(*time, status)),
+                _ => Ok(Entry::Info(key.to_owned(), value.to_owned())),
+            }
+        }
+
+        "S" => {
+            let (signal, args) = data.split_once(' ').unwrap_or((data, ""));
+
+            let arg = |name: &str| {
+                args.split(' ')
+                    .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+                    .and_then(|v| v.parse().ok())
+            };
+
+            match (signal, arg("COLS"), arg("ROWS")) {
+                ("SIGWINCH", Some(cols), Some(rows)) => Ok(Entry::Resize(*time, cols, rows)),
+                _ => Ok(Entry::Info(signal.to_owned(), args.to_owned())),
+            }
+        }
+
+        _ => Err(anyhow!("unknown entry type: {first}")),
+    }
+}
+
+/// Parses `KEY="value"` fields from the bracketed part of the typescript
+/// header line, e.g. `Script started on ... [TERM="xterm" COLUMNS="80"]`.
+fn header_fields(line: &str) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut rest = line.split_once('[').map(|(_, rest)| rest).unwrap_or("");
+
+    while let Some((key, value)) = rest.split_once("=\"") {
+        let Some((value, tail)) = value.split_once('"') else {
+            break;
+        };
+
+        fields.push((key.trim().to_owned(), value.to_owned()));
+        rest = tail;
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asciicast::Event;
+
+    fn open(typescript: &str, timing: &str) -> (u16, u16, Option<String>, Vec<Event>) {
+        let cast = super::open(typescript.as_bytes(), timing.as_bytes(), (80, 24)).unwrap();
+        let events = cast.events.collect::<anyhow::Result<Vec<_>>>().unwrap();
+
+        (
+            cast.header.term_cols,
+            cast.header.term_rows,
+            cast.header.command,
+            events,
+        )
+    }
+
+    #[test]
+    fn classic() {
+        let typescript = concat!(
+            "Script started on 2024-01-01 12:00:00+00:00 [COMMAND=\"ls -l\" TERM=\"xterm\" TTY=\"/dev/pts/0\" COLUMNS=\"100\" LINES=\"30\"]\n",
+            "$ zażółć\r\n",
+            "\nScript done on 2024-01-01 12:00:01+00:00 [COMMAND_EXIT_CODE=\"0\"]\n",
+        );
+
+        // the multibyte "ż" is split between the two chunks
+        let timing = "0.5 5\n0.25 9\n";
+
+        let (cols, rows, command, events) = open(typescript, timing);
+
+        assert_eq!((cols, rows), (100, 30));
+        assert_eq!(command.as_deref(), Some("ls -l"));
+
+        assert_eq!(
+            events,
+            [
+                Event::Output(0.5, "$ za".to_owned()),
+                Event::Output(0.75, "żółć\r\n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn advanced() {
+        let typescript = "$ ls\r\r\nfoo\r\n";
+
+        let timing = concat!(
+            "H 0.000000 COLUMNS 40\n",
+            "H 0.000000 LINES 10\n",
+            "O 0.500000 2\n",
+            "I 0.250000 3\n",
+            "O 0.125000 2\n",
+            "S 1.000000 SIGWINCH ROWS=12 COLS=50\n",
+            "O 0.250000 5\n",
+            "H 0.000000 DURATION 2.0\n",
+            "H 0.000000 EXIT_CODE 1\n",
+        );
+
+        let (cols, rows, command, events) = open(typescript, timing);
+
+        assert_eq!((cols, rows), (40, 10));
+        assert_eq!(command, None);
+        assert_eq!(
+            events,
+            [
+                Event::Output(0.5, "$ ".to_owned()),
+                Event::Input(0.75, "ls\r".to_owned()),
+                Event::Output(0.875, "\r\n".to_owned()),
+                Event::Resize(1.875, 50, 12),
+                Event::Output(2.125, "foo\r\n".to_owned()),
+                Event::Exit(2.125, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_size() {
+        let (cols, rows, _, events) = open("hello", "0.1 5\n");
+
+        assert_eq!((cols, rows), (80, 24));
+        assert_eq!(events, [Event::Output(0.1, "hello".to_owned())]);
+    }
+
+    #[test]
+    fn invalid_timing() {
+        let result = super::open("hello".as_bytes(), "0.1 5\nfoo\n".as_bytes(), (80, 24));
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::{anyhow, Result};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::Value;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use super::{Asciicast, Event, Header, Theme};
+use crate::theme::parse_hex_triplet;
+
+#[derive(Deserialize)]
+struct Terminalizer {
+    config: TerminalizerConfig,
+    records: Vec<TerminalizerRecord>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalizerConfig {
+    command: Option<String>,
+    #[serde(default)]
+    cols: Auto<u16>,
+    #[serde(default)]
+    rows: Auto<u16>,
+    #[serde(default)]
+    frame_delay: Auto<f64>,
+    #[serde(default)]
+    max_idle_time: Auto<f64>,
+    theme: Option<TerminalizerTheme>,
+}
+
+/// A number, or "auto" (or any other string) for the default.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Auto<T> {
+    Value(T),
+    Auto(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalizerTheme {
+    background: Option<String>,
+    foreground: Option<String>,
+    black: Option<String>,
+    red: Option<String>,
+    green: Option<String>,
+    yellow: Option<String>,
+    blue: Option<String>,
+    magenta: Option<String>,
+    cyan: Option<String>,
+    white: Option<String>,
+    bright_black: Option<String>,
+    bright_red: Option<String>,
+    bright_green: Option<String>,
+    bright_yellow: Option<String>,
+    bright_blue: Option<String>,
+    bright_magenta: Option<String>,
+    bright_cyan: Option<String>,
+    bright_white: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TerminalizerRecord {
+    delay: f64,
+    content: String,
+}
+
+pub fn load(yaml: &str) -> Result<Asciicast<'static>> {
+    let document = YamlLoader::load_from_str(yaml)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("empty terminalizer recording"))?;
+
+    let terminalizer: Terminalizer = serde_json::from_value(json(document))?;
+    let config = terminalizer.config;
+
+    let header = Header {
+        term_cols: config.cols.value().filter(|c| *c > 0).unwrap_or(80),
+        term_rows: config.rows.value().filter(|r| *r > 0).unwrap_or(24),
+        term_theme: config.theme.as_ref().and_then(theme),
+        idle_time_limit: config.max_idle_time.value().map(|ms| ms / 1000.0),
+        command: config.command,
+        ..Default::default()
+    };
+
+    // a fixed frame delay, when set, replaces the recorded delays
+    let frame_delay = config.frame_delay.value();
+
+    let events = terminalizer
+        .records
+        .into_iter()
+        .scan(0.0, move |time, record| {
+            *time += frame_delay.unwrap_or(record.delay) / 1000.0;
+
+            Some(Ok(Event::Output(*time, record.content)))
+        });
+
+    Ok(Asciicast {
+        header,
+        events: Box::new(events),
+    })
+}
+
+impl<T> Default for Auto<T> {
+    fn default() -> Self {
+        Auto::Auto(IgnoredAny)
+    }
+}
+
+impl<T> Auto<T> {
+    fn value(self) -> Option<T> {
+        match self {
+            Auto::Value(value) => Some(value),
+            Auto::Auto(_) => None,
+        }
+    }
+}
+
+/// Converts a YAML document to JSON, so that it can be deserialized with
+/// serde. Aliases and non-string keys, which Terminalizer doesn't write, are
+/// dropped.
+fn json(yaml: Yaml) -> Value {
+    match yaml {
+        Yaml::Real(_) => yaml.as_f64().map_or(Value::Null, Value::from),
+        Yaml::Integer(i) => i.into(),
+        Yaml::String(s) => s.into(),
+        Yaml::Boolean(b) => b.into(),
+        Yaml::Array(items) => items.into_iter().map(json).collect(),
+
+        Yaml::Hash(hash) => hash
+            .into_iter()
+            .filter_map(|(key, value)| match key {
+                Yaml::String(key) => Some((key, json(value))),
+                _ => None,
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null,
+    }
+}
+
+/// Maps the theme block onto a palette. Bright colors default to their
+/// normal counterparts, and a transparent (or otherwise non-hex) background
+/// becomes the black color.
+fn theme(theme: &TerminalizerTheme) -> Option<Theme> {
+    let color = |c: &Option<String>| parse_hex_triplet(c.as_deref()?.strip_prefix('#')?).ok();
+
+    let normal = [
+        &theme.black,
+        &theme.red,
+        &theme.green,
+        &theme.yellow,
+        &theme.blue,
+        &theme.magenta,
+        &theme.cyan,
+        &theme.white,
+    ]
+    .map(color);
+
+    let bright = [
+        &theme.bright_black,
+        &theme.bright_red,
+        &theme.bright_green,
+        &theme.bright_yellow,
+        &theme.bright_blue,
+        &theme.bright_magenta,
+        &theme.bright_cyan,
+        &theme.bright_white,
+    ]
+    .map(color);
+
+    let normal = normal.into_iter().collect::<Option<Vec<_>>>()?;
+
+    let bright = bright
+        .into_iter()
+        .zip(&normal)
+        .map(|(bright, normal)| bright.unwrap_or(*normal));
+
+    let mut palette = normal.clone();
+    palette.extend(bright);
+
+    Some(Theme {
+        background: color(&theme.background).unwrap_or(palette[0]),
+        foreground: color(&theme.foreground)?,
+        palette,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asciicast::{self, Event};
+    use crate::events::Timed;
+    use rgb::RGB8;
+
+    const YAML: &str = r##"# The configurations that used for the recording, feel free to edit them
+config:
+  command: bash -l
+  cols: 60
+  rows: auto
+  frameDelay: auto
+  maxIdleTime: 2000
+  theme:
+    background: "transparent"
+    foreground: "#afafaf"
+    cursor: "#c7c7c7"
+    black: "#232628"
+    red: "#fc4384"
+    green: "#b3e33b"
+    yellow: "#ffa727"
+    blue: "#75dff2"
+    magenta: "#ae89fe"
+    cyan: "#708387"
+    white: "#d5d5d0"
+    brightBlack: "#626566"
+    brightRed: "#ff7fac"
+# Records, feel free to edit them
+records:
+  - delay: 500
+    content: "$ "
+  - delay: 250
+    content: "ls\r\n"
+  - delay: 1000
+    content: "\e[32mfoo\e[0m\r\n"
+"##;
+
+    #[test]
+    fn load() {
+        let cast = asciicast::open(YAML.as_bytes()).unwrap();
+
+        assert_eq!(cast.header.term_cols, 60);
+        assert_eq!(cast.header.term_rows, 24);
+        assert_eq!(cast.header.idle_time_limit, Some(2.0));
+        assert_eq!(cast.header.command.as_deref(), Some("bash -l"));
+
+        let theme = cast.header.term_theme.unwrap();
+
+        assert_eq!(theme.background, RGB8::new(0x23, 0x26, 0x28));
+        assert_eq!(theme.foreground, RGB8::new(0xaf, 0xaf, 0xaf));
+        assert_eq!(theme.palette.len(), 16);
+        assert_eq!(theme.palette[1], RGB8::new(0xfc, 0x43, 0x84));
+        assert_eq!(theme.palette[9], RGB8::new(0xff, 0x7f, 0xac));
+        assert_eq!(theme.palette[10], RGB8::new(0xb3, 0xe3, 0x3b));
+
+        let events: Vec<Event> = cast.events.map(Result::unwrap).collect();
+
+        assert_eq!(
+            events,
+            [
+                Event::Output(0.5, "$ ".to_owned()),
+                Event::Output(0.75, "ls\r\n".to_owned()),
+                Event::Output(1.75, "\x1b[32mfoo\x1b[0m\r\n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_delay() {
+        let yaml = YAML.replace("frameDelay: auto", "frameDelay: 125");
+        let cast = asciicast::open(yaml.as_bytes()).unwrap();
+        let times: Vec<f64> = cast.events.map(|e| e.unwrap().time()).collect();
+
+        assert_eq!(times, [0.125, 0.25, 0.375]);
+    }
+}
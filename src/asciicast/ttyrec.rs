@@ -0,0 +1,149 @@
+use std::io::Read;
+
+use anyhow::{bail, Result};
+
+use super::{infer_size, Asciicast, Event, Header, Utf8Decoder};
+
+pub const HEADER_LEN: usize = 12;
+
+/// Tells whether the input starts with a ttyrec record header: seconds,
+/// microseconds and data length, all little-endian u32. The microseconds
+/// field of a text file (like asciicast JSON) is never below a million.
+pub fn detect(head: &[u8]) -> bool {
+    head.len() == HEADER_LEN && u32_at(head, 4) < 1_000_000
+}
+
+pub fn load<R: Read>(mut reader: R) -> Result<Asciicast<'static>> {
+    let mut decoder = Utf8Decoder::default();
+    let mut events = Vec::new();
+    let mut start = None;
+
+    loop {
+        let mut head = Vec::with_capacity(HEADER_LEN);
+        reader
+            .by_ref()
+            .take(HEADER_LEN as u64)
+            .read_to_end(&mut head)?;
+
+        if head.is_empty() {
+            break;
+        }
+
+        if head.len() < HEADER_LEN {
+            bail!("truncated ttyrec record header");
+        }
+
+        let time = u32_at(&head, 0) as f64 + u32_at(&head, 4) as f64 / 1_000_000.0;
+        let len = u32_at(&head, 8) as usize;
+        let mut data = Vec::with_capacity(len);
+        reader.by_ref().take(len as u64).read_to_end(&mut data)?;
+
+        if data.len() < len {
+            bail!("truncated ttyrec record data");
+        }
+
+        // timestamps are absolute, make them relative to the first record
+        let start = *start.get_or_insert(time);
+        events.push(Event::Output(time - start, decoder.decode(&data)));
+    }
+
+    // flush an incomplete sequence at the very end
+    let rest = decoder.finish();
+
+    if let Some(Event::Output(_, data)) = events.last_mut() {
+        data.push_str(&rest);
+    }
+
+    // ttyrec doesn't store the terminal size
+    let (term_cols, term_rows) = infer_size(&events);
+
+    let header = Header {
+        term_cols,
+        term_rows,
+        ..Default::default()
+    };
+
+    Ok(Asciicast {
+        header,
+        events: Box::new(events.into_iter().map(Ok)),
+    })
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asciicast::{self, Event};
+
+    fn record(sec: u32, usec: u32, data: &[u8]) -> Vec<u8> {
+        let mut record = Vec::new();
+        record.extend_from_slice(&sec.to_le_bytes());
+        record.extend_from_slice(&usec.to_le_bytes());
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        record.extend_from_slice(data);
+
+        record
+    }
+
+    #[test]
+    fn load() {
+        let ttyrec = [
+            record(1700000000, 500000, b"foo \xc5"),
+            record(1700000001, 0, b"\xbc\x1b[30;100H"),
+            record(1700000001, 250000, b"\x1b[8;10;20t"),
+        ]
+        .concat();
+
+        let cast = asciicast::open(ttyrec.as_slice()).unwrap();
+
+        assert_eq!(cast.header.term_cols, 100);
+        assert_eq!(cast.header.term_rows, 30);
+
+        let events: Vec<Event> = cast.events.map(Result::unwrap).collect();
+
+        assert_eq!(
+            events,
+            [
+                Event::Output(0.0, "foo ".to_owned()),
+                Event::Output(0.5, "ż\x1b[30;100H".to_owned()),
+                Event::Output(0.75, "\x1b[8;10;20t".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn small_terminal() {
+        let ttyrec = [
+            record(1700000000, 0, b"\x1b[10;40Hfoo"),
+            record(1700000000, 500000, b"bar \xc5"),
+        ]
+        .concat();
+
+        let cast = asciicast::open(ttyrec.as_slice()).unwrap();
+
+        assert_eq!(cast.header.term_cols, 40);
+        assert_eq!(cast.header.term_rows, 10);
+
+        let events: Vec<Event> = cast.events.map(Result::unwrap).collect();
+
+        assert_eq!(events[1], Event::Output(0.5, "bar \u{fffd}".to_owned()));
+    }
+
+    #[test]
+    fn detect() {
+        assert!(super::detect(&record(1700000000, 999999, b"")));
+        assert!(!super::detect(b"{\"version\": "));
+        assert!(!super::detect(b"{\n  \"version"));
+        assert!(!super::detect(b"{}"));
+    }
+
+    #[test]
+    fn truncated() {
+        let mut ttyrec = record(1700000000, 0, b"foo");
+        ttyrec.pop();
+
+        assert!(asciicast::open(ttyrec.as_slice()).is_err());
+    }
+}
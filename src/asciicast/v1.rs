@@ -8,6 +8,8 @@ struct V1 {
     version: u8,
     width: u16,
     height: u16,
+    title: Option<String>,
+    command: Option<String>,
     stdout: Vec<V1OutputEvent>,
 }
 
@@ -17,8 +19,8 @@ struct V1OutputEvent {
     data: String,
 }
 
-pub fn load(json: String) -> Result<Asciicast<'static>> {
-    let asciicast: V1 = serde_json::from_str(&json)?;
+pub fn load(json: &str) -> Result<Asciicast<'static>> {
+    let asciicast: V1 = serde_json::from_str(json)?;
 
     if asciicast.version != 1 {
         bail!("unsupported asciicast version")
@@ -27,6 +29,8 @@ pub fn load(json: String) -> Result<Asciicast<'static>> {
     let header = Header {
         term_cols: asciicast.width,
         term_rows: asciicast.height,
+        title: asciicast.title,
+        command: asciicast.command,
         ..Default::default()
     };
 
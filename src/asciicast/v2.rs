@@ -1,9 +1,10 @@
 use std::io;
 
 use anyhow::{bail, Context, Result};
+use log::warn;
 use serde::{Deserialize, Deserializer};
 
-use super::{Asciicast, Event, Header, Theme};
+use super::{parse_size, Asciicast, Event, Header, Theme};
 
 #[derive(Deserialize)]
 struct V2Header {
@@ -12,6 +13,8 @@ struct V2Header {
     height: u16,
     idle_time_limit: Option<f64>,
     theme: Option<V2Theme>,
+    title: Option<String>,
+    command: Option<String>,
 }
 
 #[derive(Deserialize, Clone)]
@@ -68,6 +71,8 @@ impl Parser {
             term_rows: self.0.height,
             term_theme,
             idle_time_limit: self.0.idle_time_limit,
+            title: self.0.title,
+            command: self.0.command,
         };
 
         let events = Box::new(lines.filter_map(parse_line));
@@ -95,7 +100,16 @@ fn parse_event(line: String) -> Result<Option<Event>> {
 
     let output = match event.code {
         V2EventCode::Output => Some(Event::Output(event.time, event.data)),
+        V2EventCode::Input => Some(Event::Input(event.time, event.data)),
         V2EventCode::Marker => Some(Event::Marker(event.time, event.data)),
+        V2EventCode::Resize => match parse_size(&event.data) {
+            Ok((cols, rows)) => Some(Event::Resize(event.time, cols, rows)),
+
+            Err(e) => {
+                warn!("skipping resize event at {:.3}s: {}", event.time, e);
+                None
+            }
+        },
         _ => None,
     };
 
@@ -1,15 +1,19 @@
-use std::io;
+use std::io::{self, Write};
 
 use anyhow::{bail, Context, Result};
+use log::warn;
 use serde::{Deserialize, Deserializer};
+use serde_json::json;
 
-use super::{Asciicast, Event, Header, Theme};
+use super::{parse_size, Asciicast, Event, Header, Theme};
 
 #[derive(Deserialize)]
 struct V3Header {
     version: u8,
     term: V3Term,
     idle_time_limit: Option<f64>,
+    title: Option<String>,
+    command: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -83,6 +87,8 @@ impl Parser {
             term_rows: self.header.term.rows,
             term_theme,
             idle_time_limit: self.header.idle_time_limit,
+            title: self.header.title.take(),
+            command: self.header.command.take(),
         };
 
         let events = Box::new(lines.filter_map(move |line| self.parse_line(line)));
@@ -112,7 +118,27 @@ impl Parser {
 
         let output = match event.code {
             V3EventCode::Output => Some(Event::Output(time, event.data)),
+            V3EventCode::Input => Some(Event::Input(time, event.data)),
             V3EventCode::Marker => Some(Event::Marker(time, event.data)),
+            V3EventCode::Resize => match parse_size(&event.data) {
+                Ok((cols, rows)) => Some(Event::Resize(time, cols, rows)),
+
+                Err(e) => {
+                    warn!("skipping resize event at {:.3}s: {}", time, e);
+                    None
+                }
+            },
+            V3EventCode::Exit => match event.data.parse() {
+                Ok(status) => Some(Event::Exit(time, status)),
+
+                Err(_) => {
+                    warn!(
+                        "skipping exit event at {:.3}s: invalid status {}",
+                        time, event.data
+                    );
+                    None
+                }
+            },
             _ => None,
         };
 
@@ -120,6 +146,77 @@ impl Parser {
     }
 }
 
+pub fn write<W: Write>(
+    mut output: W,
+    header: &Header,
+    events: impl Iterator<Item = Result<Event>>,
+) -> Result<()> {
+    let mut term = json!({
+        "cols": header.term_cols,
+        "rows": header.term_rows,
+    });
+
+    if let Some(theme) = &header.term_theme {
+        term["theme"] = json!({
+            "fg": hex_color(theme.foreground),
+            "bg": hex_color(theme.background),
+            "palette": theme.palette.iter().copied().map(hex_color).collect::<Vec<_>>().join(":"),
+        });
+    }
+
+    // written by hand to keep "version" first
+    write!(output, r#"{{"version":3,"term":{term}"#)?;
+
+    if let Some(limit) = header.idle_time_limit {
+        write!(output, r#","idle_time_limit":{}"#, json!(limit))?;
+    }
+
+    if let Some(command) = &header.command {
+        write!(output, r#","command":{}"#, json!(command))?;
+    }
+
+    if let Some(title) = &header.title {
+        write!(output, r#","title":{}"#, json!(title))?;
+    }
+
+    writeln!(output, "}}")?;
+
+    // intervals are computed on whole microseconds so that rounding errors
+    // don't accumulate over the file
+    let mut prev_micros = 0;
+
+    for event in events {
+        let (time, code, data) = match event? {
+            Event::Output(time, data) => (time, "o", data),
+            Event::Input(time, data) => (time, "i", data),
+            Event::Marker(time, label) => (time, "m", label),
+            Event::Resize(time, cols, rows) => (time, "r", format!("{cols}x{rows}")),
+            Event::Exit(time, status) => (time, "x", status.to_string()),
+        };
+
+        let micros = (time * 1_000_000.0).round().max(0.0) as u64;
+        let interval = micros.saturating_sub(prev_micros);
+        prev_micros = prev_micros.max(micros);
+
+        writeln!(
+            output,
+            "[{}.{:06}, \"{}\", {}]",
+            interval / 1_000_000,
+            interval % 1_000_000,
+            code,
+            serde_json::to_string(&data)?
+        )?;
+    }
+
+    output.flush()?;
+
+    Ok(())
+}
+
+fn hex_color(color: rgb::RGB8) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
 fn deserialize_code<'de, D>(deserializer: D) -> Result<V3EventCode, D::Error>
 where
     D: Deserializer<'de>,
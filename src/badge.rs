@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use imgref::ImgVec;
+use log::debug;
+use rgb::RGBA8;
+use tiny_skia::Pixmap;
+
+use crate::renderer::composite;
+
+const SUCCESS_COLOR: &str = "#2ea043";
+const FAILURE_COLOR: &str = "#da3633";
+const LABEL_COLOR: &str = "#000000b3";
+
+#[derive(Clone)]
+pub struct Settings {
+    pub font_db: fontdb::Database,
+    pub font_families: Vec<String>,
+    pub font_size: usize,
+}
+
+/// A small pill drawn in a corner of frames, e.g. the exit status of the
+/// recorded command in the top-right corner of the final frame: a check mark
+/// for success, the status code otherwise.
+pub struct Badge {
+    pixmap: Pixmap,
+    margin: usize,
+}
+
+impl Badge {
+    pub fn new(status: i32, settings: Settings) -> Option<Self> {
+        let font_size = settings.font_size as f64;
+        let height = font_size * 1.6;
+        let icon = height * 0.5;
+        let padding = (height - icon) / 2.0;
+        let stroke_width = font_size * 0.15;
+
+        let (width, color, icon_path, label) = if status == 0 {
+            let path = format!(
+                "M {:.1} {:.1} L {:.1} {:.1} L {:.1} {:.1}",
+                padding + icon * 0.1,
+                padding + icon * 0.55,
+                padding + icon * 0.4,
+                padding + icon * 0.85,
+                padding + icon * 0.95,
+                padding + icon * 0.15
+            );
+
+            (height, SUCCESS_COLOR, path, String::new())
+        } else {
+            let text = status.to_string();
+            let width = height + text.len() as f64 * font_size * 0.6 + padding * 0.5;
+
+            let path = format!(
+                "M {0:.1} {0:.1} L {1:.1} {1:.1} M {0:.1} {1:.1} L {1:.1} {0:.1}",
+                padding + icon * 0.15,
+                padding + icon * 0.85
+            );
+
+            let label = format!(
+                r#"<text x="{:.1}" y="{:.1}" style="fill: white">{text}</text>"#,
+                padding * 1.5 + icon,
+                height / 2.0 + font_size * 0.35
+            );
+
+            (width, FAILURE_COLOR, path, label)
+        };
+
+        let content = format!(
+            r#"<path d="{icon_path}" style="fill: none; stroke: white; stroke-width: {stroke_width:.1}; stroke-linecap: round; stroke-linejoin: round" />{label}"#
+        );
+
+        Self::render(&content, (width, height), color, settings)
+    }
+
+    /// A pill with the given text, e.g. the current playback speed.
+    pub fn label(text: &str, settings: Settings) -> Option<Self> {
+        let font_size = settings.font_size as f64;
+        let height = font_size * 1.6;
+        let padding = height * 0.4;
+        let width = text.chars().count() as f64 * font_size * 0.6 + padding * 2.0;
+
+        let content = format!(
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" style="fill: white">{text}</text>"#,
+            width / 2.0,
+            height / 2.0 + font_size * 0.35
+        );
+
+        Self::render(&content, (width, height), LABEL_COLOR, settings)
+    }
+
+    fn render(
+        content: &str,
+        (width, height): (f64, f64),
+        color: &str,
+        settings: Settings,
+    ) -> Option<Self> {
+        let svg = format!(
+            r#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height:.1}" font-size="{}px" font-family="{}" font-weight="bold"><rect width="{width:.1}" height="{height:.1}" rx="{:.1}" style="fill: {color}" />{content}</svg>"#,
+            settings.font_size,
+            settings.font_families.join(","),
+            height / 2.0
+        );
+
+        let options = usvg::Options {
+            fontdb: Arc::new(settings.font_db),
+            ..Default::default()
+        };
+
+        let tree = usvg::Tree::from_str(&svg, &options)
+            .map_err(|e| debug!("invalid badge svg: {e}"))
+            .ok()?;
+
+        let mut pixmap = Pixmap::new(width.ceil() as u32, height.ceil() as u32)?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+        Some(Self {
+            pixmap,
+            margin: settings.font_size / 2,
+        })
+    }
+
+    pub fn apply(&self, image: &mut ImgVec<RGBA8>) {
+        let x = image
+            .width()
+            .saturating_sub(self.pixmap.width() as usize + self.margin);
+
+        composite(image, &self.pixmap, (x, self.margin), 1.0);
+    }
+
+    pub fn apply_left(&self, image: &mut ImgVec<RGBA8>) {
+        composite(image, &self.pixmap, (self.margin, self.margin), 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imgref::ImgVec;
+    use rgb::RGBA8;
+
+    use super::{Badge, Settings};
+
+    fn settings() -> Settings {
+        Settings {
+            font_db: fontdb::Database::new(),
+            font_families: Vec::new(),
+            font_size: 10,
+        }
+    }
+
+    #[test]
+    fn exit_status() {
+        let black = RGBA8::new(0, 0, 0, 255);
+
+        // a 16px high pill, 5px away from the top-right corner
+        for (status, color, left) in [
+            (0, RGBA8::new(0x2e, 0xa0, 0x43, 255), 19usize),
+            (1, RGBA8::new(0xda, 0x36, 0x33, 255), 11),
+        ] {
+            let badge = Badge::new(status, settings()).unwrap();
+            let mut image = ImgVec::new(vec![black; 40 * 30], 40, 30);
+            badge.apply(&mut image);
+
+            assert_eq!(image[(left + 2, 13usize)], color);
+            assert_eq!(image[(left - 1, 13usize)], black);
+            assert_eq!(image[(left + 2, 4usize)], black);
+            assert_eq!(image[(left + 2, 21usize)], black);
+            assert_eq!(image[(35usize, 13usize)], black);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use anyhow::{Context, Result};
+use log::info;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Wraps the input in a decoder matching its magic bytes, so compressed
+/// recordings can be read without unpacking them first. Uncompressed input
+/// is passed through as is.
+pub fn decompress<'a, R: Read + 'a>(mut reader: R) -> Result<Box<dyn BufRead + 'a>> {
+    let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+    reader
+        .by_ref()
+        .take(XZ_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+
+    let reader = Cursor::new(magic.clone()).chain(reader);
+
+    if magic.starts_with(GZIP_MAGIC) {
+        info!("input compression: gzip");
+
+        Ok(Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(
+            reader,
+        ))))
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        info!("input compression: zstd");
+
+        let decoder =
+            ruzstd::decoding::StreamingDecoder::new(reader).context("invalid zstd stream")?;
+
+        Ok(Box::new(BufReader::new(decoder)))
+    } else if magic.starts_with(XZ_MAGIC) {
+        info!("input compression: xz");
+
+        // lzma-rs has no streaming decoder, recordings are small enough to be
+        // decompressed up front
+        let mut data = Vec::new();
+        lzma_rs::xz_decompress(&mut BufReader::new(reader), &mut data)
+            .context("invalid xz stream")?;
+
+        Ok(Box::new(Cursor::new(data)))
+    } else {
+        Ok(Box::new(BufReader::new(reader)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    const CAST: &str = "{\"version\": 2, \"width\": 80, \"height\": 24}\n[1.0, \"o\", \"hello\"]\n";
+
+    fn decompress(data: &[u8]) -> String {
+        let mut output = String::new();
+
+        super::decompress(data)
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+
+        output
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(decompress(CAST.as_bytes()), CAST);
+        assert_eq!(decompress(b"{}"), "{}");
+        assert_eq!(decompress(b""), "");
+    }
+
+    #[test]
+    fn gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(CAST.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+
+        assert_eq!(decompress(&data), CAST);
+    }
+
+    #[test]
+    fn zstd() {
+        let data = ruzstd::encoding::compress_to_vec(
+            CAST.as_bytes(),
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+
+        assert_eq!(decompress(&data), CAST);
+    }
+
+    #[test]
+    fn xz() {
+        let mut data = Vec::new();
+        lzma_rs::xz_compress(&mut CAST.as_bytes(), &mut data).unwrap();
+
+        assert_eq!(decompress(&data), CAST);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::iter;
+
+use crate::asciicast::{Asciicast, Event, Header};
+use crate::events::Timed;
+use crate::{ConcatTransition, ConcatVt};
+
+const RESET: &str = "\x1bc";
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+pub struct Settings {
+    /// How long the final screen of each part stays up before the next one
+    pub hold: f64,
+    pub gap: f64,
+    pub transition: ConcatTransition,
+    pub vt: ConcatVt,
+}
+
+/// Splices recordings into one, each part starting where the previous one
+/// ended (plus the hold of its final screen, and the gap). All parts play on
+/// a common grid, large enough for the initial terminal size of every one of
+/// them.
+pub fn concat<'a>(casts: Vec<Asciicast<'a>>, settings: Settings) -> Asciicast<'a> {
+    let header = header(&casts);
+    let size = (header.term_cols as usize, header.term_rows as usize);
+    let count = casts.len();
+
+    let mut parts: VecDeque<_> = casts
+        .into_iter()
+        .map(|cast| cast.events)
+        .enumerate()
+        .collect();
+
+    let mut current = None;
+    let mut queue: VecDeque<Event> = VecDeque::new();
+    let mut offset = 0.0;
+    let mut end: f64 = 0.0;
+
+    let events = iter::from_fn(move || loop {
+        if let Some(event) = queue.pop_front() {
+            return Some(Ok(event));
+        }
+
+        let Some((i, events)) = &mut current else {
+            let (i, events) = parts.pop_front()?;
+
+            if i > 0 {
+                // undo resizes done during the previous part
+                queue.push_back(Event::Resize(offset, size.0, size.1));
+
+                if settings.vt == ConcatVt::Reset {
+                    queue.push_back(Event::Output(offset, RESET.to_owned()));
+                }
+            }
+
+            current = Some((i, events));
+            continue;
+        };
+
+        match events.next() {
+            Some(Ok(mut event)) => {
+                // only the exit of the last part ends the timeline
+                if matches!(event, Event::Exit(..)) && *i + 1 < count {
+                    continue;
+                }
+
+                event.set_time(offset + event.time());
+                end = end.max(event.time());
+
+                return Some(Ok(event));
+            }
+
+            Some(Err(e)) => return Some(Err(e)),
+
+            None => {
+                current = None;
+
+                // let the final screen be seen before the next part draws over it
+                let start = end + settings.hold;
+
+                if !parts.is_empty() && settings.transition == ConcatTransition::Clear {
+                    queue.push_back(Event::Output(start, CLEAR.to_owned()));
+                }
+
+                offset = start + settings.gap;
+            }
+        }
+    });
+
+    Asciicast {
+        header,
+        events: Box::new(events),
+    }
+}
+
+/// Header of the spliced recording: the common grid, plus metadata taken from
+/// the first part which has it.
+fn header(casts: &[Asciicast]) -> Header {
+    let headers = || casts.iter().map(|cast| &cast.header);
+
+    Header {
+        term_cols: headers().map(|h| h.term_cols).max().unwrap_or(80),
+        term_rows: headers().map(|h| h.term_rows).max().unwrap_or(24),
+        term_theme: headers().find_map(|h| h.term_theme.clone()),
+        idle_time_limit: headers().find_map(|h| h.idle_time_limit),
+        title: headers().find_map(|h| h.title.clone()),
+        command: headers().find_map(|h| h.command.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Settings;
+    use crate::asciicast::{self, Event};
+    use crate::{ConcatTransition, ConcatVt};
+
+    fn casts() -> Vec<asciicast::Asciicast<'static>> {
+        let first = concat!(
+            "{\"version\": 2, \"width\": 10, \"height\": 3, \"title\": \"one\"}\n",
+            "[0.5, \"o\", \"foo\"]\n",
+            "[1.0, \"r\", \"20x2\"]\n",
+            "[2.0, \"o\", \"bar\"]\n",
+        );
+
+        let second = concat!(
+            "{\"version\": 3, \"term\": {\"cols\": 15, \"rows\": 5}}\n",
+            "[0.25, \"o\", \"baz\"]\n",
+            "[0.25, \"x\", \"0\"]\n",
+        );
+
+        vec![
+            asciicast::open(first.as_bytes()).unwrap(),
+            asciicast::open(second.as_bytes()).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn concat() {
+        let settings = Settings {
+            hold: 0.5,
+            gap: 0.5,
+            transition: ConcatTransition::Hold,
+            vt: ConcatVt::Reset,
+        };
+
+        let cast = super::concat(casts(), settings);
+
+        assert_eq!(cast.header.term_cols, 15);
+        assert_eq!(cast.header.term_rows, 5);
+        assert_eq!(cast.header.title.as_deref(), Some("one"));
+
+        let events: Vec<Event> = cast.events.map(Result::unwrap).collect();
+
+        assert_eq!(
+            events,
+            [
+                Event::Output(0.5, "foo".to_owned()),
+                Event::Resize(1.0, 20, 2),
+                Event::Output(2.0, "bar".to_owned()),
+                Event::Resize(3.0, 15, 5),
+                Event::Output(3.0, "\x1bc".to_owned()),
+                Event::Output(3.25, "baz".to_owned()),
+                Event::Exit(3.5, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn concat_clear_preserve() {
+        let settings = Settings {
+            hold: 1.0,
+            gap: 0.0,
+            transition: ConcatTransition::Clear,
+            vt: ConcatVt::Preserve,
+        };
+
+        let events: Vec<Event> = super::concat(casts(), settings)
+            .events
+            .map(Result::unwrap)
+            .collect();
+
+        // the final screen of the first part stays up for the hold
+        assert_eq!(events[2], Event::Output(2.0, "bar".to_owned()));
+        assert_eq!(events[3], Event::Output(3.0, "\x1b[2J\x1b[H".to_owned()));
+        assert_eq!(events[4], Event::Resize(3.0, 15, 5));
+        assert_eq!(events[5], Event::Output(3.25, "baz".to_owned()));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use imgref::ImgVec;
+use rgb::RGBA8;
+
+use crate::encoder;
+use crate::theme::Theme;
+
+pub struct Settings {
+    pub columns: usize,
+    pub scale: f64,
+    pub spacing: usize,
+    pub font_db: fontdb::Database,
+    pub font_families: Vec<String>,
+    pub font_size: usize,
+    pub theme: Theme,
+}
+
+/// Lays out labelled thumbnails in a grid and returns the result encoded as
+/// PNG.
+pub fn render(thumbnails: Vec<(String, ImgVec<RGBA8>)>, settings: Settings) -> Result<Vec<u8>> {
+    let (first_width, first_height) = thumbnails
+        .first()
+        .map(|(_, image)| (image.width(), image.height()))
+        .ok_or_else(|| anyhow!("no frames to put on the contact sheet"))?;
+
+    let columns = settings.columns.clamp(1, thumbnails.len());
+    let rows = thumbnails.len().div_ceil(columns);
+    let thumb_width = (first_width as f64 * settings.scale).round();
+    let thumb_height = (first_height as f64 * settings.scale).round();
+    let spacing = settings.spacing as f64;
+    let font_size = (settings.font_size as f64 * settings.scale).max(10.0);
+    let label_height = font_size * 1.5;
+    let cell_width = thumb_width + spacing;
+    let cell_height = thumb_height + label_height + spacing;
+    let width = spacing + columns as f64 * cell_width;
+    let height = spacing + rows as f64 * cell_height;
+    let theme = &settings.theme;
+
+    let mut svg = format!(
+        r#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="{}" font-size="{}px" font-family="{}">
+<rect width="100%" height="100%" style="fill: {}" />
+"#,
+        width,
+        height,
+        font_size,
+        settings.font_families.join(","),
+        theme.background
+    );
+
+    for (i, (label, image)) in thumbnails.iter().enumerate() {
+        let x = spacing + (i % columns) as f64 * cell_width;
+        let y = spacing + (i / columns) as f64 * cell_height;
+        let mut png = Vec::new();
+        encoder::write_png(&mut png, image.as_ref())?;
+        let data = base64::engine::general_purpose::STANDARD.encode(&png);
+
+        let _ = writeln!(
+            svg,
+            r#"<image x="{x}" y="{y}" width="{thumb_width}" height="{thumb_height}" xlink:href="data:image/png;base64,{data}" />"#
+        );
+
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" style="fill: {}">{}</text>"#,
+            x + thumb_width / 2.0,
+            y + thumb_height + font_size * 1.2,
+            theme.foreground,
+            escape(label)
+        );
+    }
+
+    svg.push_str("</svg>");
+
+    let options = usvg::Options {
+        fontdb: Arc::new(settings.font_db),
+        ..Default::default()
+    };
+
+    let tree = usvg::Tree::from_str(&svg, &options)?;
+    let mut pixmap = tiny_skia::Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| anyhow!("invalid contact sheet size: {}x{}", width, height))?;
+
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    Ok(pixmap.encode_png()?)
+}
+
+pub fn format_time(time: f64) -> String {
+    let tenths = (time * 10.0).round() as u64;
+    let minutes = tenths / 600;
+    let seconds = (tenths % 600) as f64 / 10.0;
+
+    format!("{minutes}:{seconds:04.1}")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn format_time() {
+        assert_eq!(super::format_time(0.0), "0:00.0");
+        assert_eq!(super::format_time(9.96), "0:10.0");
+        assert_eq!(super::format_time(83.42), "1:23.4");
+    }
+}
@@ -0,0 +1,391 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use log::info;
+
+use crate::asciicast::Event;
+use crate::pipeline::Stage;
+use crate::{events, Config};
+
+/// A point in time to trim the recording at, either in seconds or at the
+/// first marker with the given label.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrimPoint {
+    Seconds(f64),
+    Marker(String),
+}
+
+impl FromStr for TrimPoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(label) = s.strip_prefix("marker:") {
+            return Ok(TrimPoint::Marker(label.to_owned()));
+        }
+
+        match s.parse::<f64>() {
+            Ok(secs) if secs >= 0.0 => Ok(TrimPoint::Seconds(secs)),
+            Ok(_) => Err(anyhow!("{s} is a negative number of seconds")),
+            Err(_) if s.is_empty() => Err(anyhow!("empty marker label")),
+            Err(_) => Ok(TrimPoint::Marker(s.to_owned())),
+        }
+    }
+}
+
+impl TrimPoint {
+    pub(crate) fn resolve(&self, events: &[Event]) -> Result<f64> {
+        match self {
+            TrimPoint::Seconds(secs) => Ok(*secs),
+
+            TrimPoint::Marker(label) => events
+                .iter()
+                .find_map(|event| match event {
+                    Event::Marker(time, l) if l == label => Some(*time),
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow!("no marker labeled \"{label}\"")),
+        }
+    }
+}
+
+/// A time range to cut out of the recording, written as "START-END".
+/// With marker labels on both ends, it covers every pair of such markers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CutRange {
+    range: String,
+    splits: Vec<(TrimPoint, TrimPoint)>,
+}
+
+impl FromStr for CutRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let splits: Vec<_> = range_splits(s)
+            .filter_map(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)))
+            .collect();
+
+        if splits.is_empty() {
+            return Err(anyhow!("{s} is not a START-END range"));
+        }
+
+        Ok(CutRange {
+            range: s.to_owned(),
+            splits,
+        })
+    }
+}
+
+impl CutRange {
+    pub(crate) fn resolve(&self, events: &[Event]) -> Result<Vec<(f64, f64)>> {
+        resolve_split(&self.range, &self.splits, |(start, end)| {
+            Self::resolve_split(start, end, events)
+        })
+    }
+
+    fn resolve_split(
+        start: &TrimPoint,
+        end: &TrimPoint,
+        events: &[Event],
+    ) -> Result<Vec<(f64, f64)>> {
+        let ranges = match (start, end) {
+            (TrimPoint::Marker(start), TrimPoint::Marker(end)) => {
+                let mut ranges = Vec::new();
+                let mut open = None;
+
+                for event in events {
+                    match (event, open) {
+                        (Event::Marker(time, label), Some(t)) if label == end => {
+                            ranges.push((t, *time));
+                            open = None;
+                        }
+
+                        (Event::Marker(time, label), None) if label == start => {
+                            open = Some(*time);
+                        }
+
+                        _ => (),
+                    }
+                }
+
+                if ranges.is_empty() {
+                    return Err(anyhow!("no \"{start}\" marker followed by \"{end}\""));
+                }
+
+                ranges
+            }
+
+            (start, end) => vec![(start.resolve(events)?, end.resolve(events)?)],
+        };
+
+        match ranges.iter().find(|(start, end)| end <= start) {
+            Some((start, end)) => Err(anyhow!(
+                "the cut end ({end}s) is not after its start ({start}s)"
+            )),
+
+            None => Ok(ranges),
+        }
+    }
+}
+
+/// Ways to split a "START-END" range into its ends. Marker labels can
+/// contain hyphens too, so any hyphen can be the separator.
+fn range_splits(range: &str) -> impl Iterator<Item = (&str, &str)> {
+    range
+        .match_indices('-')
+        .map(|(i, _)| (&range[..i], &range[i + 1..]))
+}
+
+/// Resolves the one split of a range whose ends resolve on the recording.
+fn resolve_split<S, T>(range: &str, splits: &[S], resolve: impl Fn(&S) -> Result<T>) -> Result<T> {
+    let mut results: Vec<Result<T>> = splits.iter().map(resolve).collect();
+
+    if results.len() == 1 {
+        return results.remove(0);
+    }
+
+    let mut resolved = results.into_iter().filter_map(Result::ok);
+
+    match (resolved.next(), resolved.next()) {
+        (Some(value), None) => Ok(value),
+        (None, _) => Err(anyhow!("no markers in the recording match {range}")),
+        (Some(_), Some(_)) => Err(anyhow!(
+            "{range} is ambiguous, it matches the recording split at different hyphens"
+        )),
+    }
+}
+
+/// Playback speed for a part of the timeline, written as "START-END:SPEED",
+/// e.g. "10-45:8x" or "marker:deploy-:1.5x". Either end can be left out to
+/// extend the segment to the start or end of the recording.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeedSegment {
+    range: String,
+    splits: Vec<(Option<TrimPoint>, Option<TrimPoint>)>,
+    pub(crate) speed: f64,
+}
+
+impl FromStr for SpeedSegment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (range, speed) = s
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("{s} is not a START-END:SPEED segment"))?;
+
+        let point = |p: &str| (!p.is_empty()).then(|| p.parse()).transpose().ok();
+
+        let splits: Vec<_> = range_splits(range)
+            .filter_map(|(start, end)| Some((point(start)?, point(end)?)))
+            .collect();
+
+        if splits.is_empty() {
+            return Err(anyhow!("{range} is not a START-END range"));
+        }
+
+        let speed = speed
+            .trim_end_matches(['x', '×'])
+            .parse::<f64>()
+            .ok()
+            .filter(|speed| *speed > 0.0)
+            .ok_or_else(|| anyhow!("{speed} is not a positive speed"))?;
+
+        Ok(SpeedSegment {
+            range: range.to_owned(),
+            splits,
+            speed,
+        })
+    }
+}
+
+impl SpeedSegment {
+    pub(crate) fn resolve(&self, events: &[Event]) -> Result<(f64, f64)> {
+        resolve_split(&self.range, &self.splits, |(start, end)| {
+            let start = match start {
+                Some(point) => point.resolve(events)?,
+                None => 0.0,
+            };
+
+            let end = match end {
+                Some(point) => point.resolve(events)?,
+                None => f64::INFINITY,
+            };
+
+            if end <= start {
+                return Err(anyhow!(
+                    "the speed segment end ({end}s) is not after its start ({start}s)"
+                ));
+            }
+
+            Ok((start, end))
+        })
+    }
+}
+
+/// --start, --end and --cut resolved on a recording, with the cuts on the
+/// trimmed timeline.
+struct Edit {
+    start: f64,
+    end: Option<f64>,
+    cuts: Vec<(f64, f64)>,
+}
+
+impl Edit {
+    /// Maps a point in recording time onto the edited timeline. Points in
+    /// removed parts end up where the removal happened.
+    fn time(&self, time: f64) -> f64 {
+        let time = self.end.map_or(time, |end| time.min(end));
+        let time = (time - self.start).max(0.0);
+
+        let removed: f64 = self
+            .cuts
+            .iter()
+            .map(|(start, end)| (time.min(*end) - start).max(0.0))
+            .sum();
+
+        time - removed
+    }
+}
+
+/// Applies --start, --end and --cut, in recording time (before idle time
+/// limiting and speed-up). Speed segments are moved onto the edited
+/// timeline.
+pub fn edit<'a>(stage: Stage<'a>, config: &Config) -> Result<Stage<'a>> {
+    if config.start.is_none() && config.end.is_none() && config.cuts.is_empty() {
+        return Ok(stage);
+    }
+
+    // markers can be anywhere in the recording
+    let events = stage.events.collect::<Result<Vec<_>>>()?;
+
+    let start = match &config.start {
+        Some(point) => point.resolve(&events)?,
+        None => 0.0,
+    };
+
+    let end = config
+        .end
+        .as_ref()
+        .map(|point| point.resolve(&events))
+        .transpose()?;
+
+    if let Some(end) = end.filter(|end| *end <= start) {
+        return Err(anyhow!(
+            "the end ({end}s) is not after the start ({start}s)"
+        ));
+    }
+
+    info!(
+        "trimming to {start}s - {}",
+        end.map_or("end".to_owned(), |end| format!("{end}s"))
+    );
+
+    let mut cuts = Vec::new();
+
+    for cut in &config.cuts {
+        for (cut_start, cut_end) in cut.resolve(&events)? {
+            info!("cutting out {cut_start}s - {cut_end}s");
+
+            // cuts apply to the trimmed timeline
+            let cut_start = (cut_start - start).max(0.0);
+            let cut_end = end.map_or(cut_end, |end| cut_end.min(end)) - start;
+
+            if cut_start < cut_end {
+                cuts.push((cut_start, cut_end));
+            }
+        }
+    }
+
+    let cuts = merge_ranges(cuts);
+    let events = events::trim(events.into_iter().map(Ok), start, end);
+    let events = events::cut(events, cuts.clone());
+
+    let edit = Edit { start, end, cuts };
+
+    let speed_segments = stage
+        .speed_segments
+        .into_iter()
+        .map(|(start, end, speed)| (edit.time(start), edit.time(end), speed))
+        .filter(|(start, end, _)| start < end)
+        .collect();
+
+    Ok(Stage {
+        events: Box::new(events),
+        speed_segments,
+    })
+}
+
+/// Sorts time ranges, joining the overlapping ones.
+fn merge_ranges(mut ranges: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CutRange, SpeedSegment};
+    use crate::asciicast::Event;
+
+    fn markers(labels: &[&str]) -> Vec<Event> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| Event::Marker(i as f64, (*label).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn cut_range() {
+        let range: CutRange = "1.5-4".parse().unwrap();
+
+        assert_eq!(range.resolve(&[]).unwrap(), [(1.5, 4.0)]);
+        assert!("4".parse::<CutRange>().is_err());
+        assert!("-4".parse::<CutRange>().is_err());
+    }
+
+    #[test]
+    fn cut_range_hyphenated_markers() {
+        let events = markers(&["deploy", "deploy-start", "build", "deploy-end"]);
+        let range: CutRange = "deploy-start-deploy-end".parse().unwrap();
+
+        assert_eq!(range.resolve(&events).unwrap(), [(1.0, 3.0)]);
+
+        let range: CutRange = "deploy-start-2.5".parse().unwrap();
+
+        assert_eq!(range.resolve(&events).unwrap(), [(1.0, 2.5)]);
+
+        let range: CutRange = "deploy-stop-deploy-end".parse().unwrap();
+
+        assert!(range.resolve(&events).is_err());
+    }
+
+    #[test]
+    fn speed_segment_hyphenated_markers() {
+        let events = markers(&["deploy", "deploy-start", "build", "deploy-end"]);
+        let segment: SpeedSegment = "deploy-start-deploy-end:8x".parse().unwrap();
+
+        assert_eq!(segment.resolve(&events).unwrap(), (1.0, 3.0));
+        assert_eq!(segment.speed, 8.0);
+
+        let segment: SpeedSegment = "marker:deploy-start-:1.5x".parse().unwrap();
+
+        assert_eq!(segment.resolve(&events).unwrap(), (1.0, f64::INFINITY));
+
+        let segment: SpeedSegment = "-2:0.5".parse().unwrap();
+
+        assert_eq!(segment.resolve(&events).unwrap(), (0.0, 2.0));
+        assert!("1-2".parse::<SpeedSegment>().is_err());
+        assert!("1-2:0x".parse::<SpeedSegment>().is_err());
+    }
+}
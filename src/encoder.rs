@@ -0,0 +1,385 @@
+mod apng;
+mod av1;
+mod png_sequence;
+mod raw;
+mod webp;
+mod y4m;
+
+use std::io::Write;
+
+use anyhow::Result;
+use imgref::{ImgRef, ImgVec};
+use rgb::{ComponentBytes, RGBA8};
+
+pub trait Encoder {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()>;
+    fn finish(&mut self) -> Result<()>;
+}
+
+pub fn webp<W: Write>(
+    output: W,
+    pixel_size: (usize, usize),
+    no_loop: bool,
+) -> webp::WebpEncoder<W> {
+    webp::WebpEncoder::new(output, pixel_size, no_loop)
+}
+
+pub fn apng<W: Write>(
+    output: W,
+    pixel_size: (usize, usize),
+    frame_count: usize,
+    no_loop: bool,
+) -> apng::ApngEncoder<W> {
+    apng::ApngEncoder::new(output, pixel_size, frame_count, no_loop)
+}
+
+pub fn av1<W: Write>(output: W, pixel_size: (usize, usize), fps: u8) -> Result<av1::Av1Encoder<W>> {
+    av1::Av1Encoder::new(output, pixel_size, fps)
+}
+
+pub fn png_sequence(
+    path: &str,
+    pixel_size: (usize, usize),
+    markers: Vec<(f64, String)>,
+) -> png_sequence::PngSequenceEncoder {
+    png_sequence::PngSequenceEncoder::new(path, pixel_size, markers)
+}
+
+pub fn y4m<W: Write>(output: W, pixel_size: (usize, usize), fps: u8) -> y4m::Y4mEncoder<W> {
+    y4m::Y4mEncoder::new(output, pixel_size, fps)
+}
+
+pub fn raw<W: Write>(output: W, fps: u8) -> raw::RawEncoder<W> {
+    raw::RawEncoder::new(output, fps)
+}
+
+pub fn write_png<W: Write>(output: W, image: ImgRef<RGBA8>) -> Result<()> {
+    let mut encoder = png::Encoder::new(output, image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let data: Vec<RGBA8> = image.pixels().collect();
+    writer.write_image_data(data.as_bytes())?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Bounding box (x, y, width, height) of the pixels that differ between two
+/// equally sized images, or `None` when the images are identical.
+fn changed_rect(prev: ImgRef<RGBA8>, next: ImgRef<RGBA8>) -> Option<(usize, usize, usize, usize)> {
+    let mut rows = prev
+        .rows()
+        .zip(next.rows())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b);
+    let (top, _) = rows.next()?;
+    let bottom = rows.next_back().map(|(y, _)| y).unwrap_or(top);
+    let mut left = prev.width();
+    let mut right = 0;
+
+    for (a, b) in prev
+        .rows()
+        .zip(next.rows())
+        .skip(top)
+        .take(bottom - top + 1)
+    {
+        if let Some(x) = a.iter().zip(b).position(|(a, b)| a != b) {
+            left = left.min(x);
+        }
+
+        if let Some(x) = a.iter().zip(b).rposition(|(a, b)| a != b) {
+            right = right.max(x);
+        }
+    }
+
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
+
+/// Converts variable length frames into repetitions of a constant frame rate.
+struct Resampler {
+    fps: u32,
+    ticks: u64,
+}
+
+impl Resampler {
+    fn new(fps: u8) -> Self {
+        Self {
+            fps: fps.max(1) as u32,
+            ticks: 0,
+        }
+    }
+
+    /// Number of ticks a frame should be repeated for, 0 when it's shorter
+    /// than a single tick and should be dropped.
+    fn repeats(&mut self, time: f64, duration: f64) -> u64 {
+        let end = ((time + duration) * self.fps as f64).round() as u64;
+        let repeats = end.saturating_sub(self.ticks);
+        self.ticks += repeats;
+
+        repeats
+    }
+}
+
+/// Converts an image to BT.709 limited range Y'CbCr 4:2:0 planes, compositing
+/// transparent pixels over black.
+fn yuv420(image: ImgRef<RGBA8>) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let (width, height) = (image.width(), image.height());
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut y_plane = vec![0u8; width * height];
+    let mut u_plane = vec![0u32; chroma_width * chroma_height];
+    let mut v_plane = vec![0u32; chroma_width * chroma_height];
+    let mut samples = vec![0u32; chroma_width * chroma_height];
+
+    for (row, pixels) in image.rows().enumerate() {
+        for (col, p) in pixels.iter().enumerate() {
+            let a = p.a as f32 / 255.0;
+            let (r, g, b) = (p.r as f32 * a, p.g as f32 * a, p.b as f32 * a);
+            let y = 16.0 + (0.1826 * r + 0.6142 * g + 0.0620 * b);
+            let u = 128.0 + (-0.1006 * r - 0.3386 * g + 0.4392 * b);
+            let v = 128.0 + (0.4392 * r - 0.3989 * g - 0.0403 * b);
+
+            y_plane[row * width + col] = y.round() as u8;
+            let idx = (row / 2) * chroma_width + col / 2;
+            u_plane[idx] += u.round() as u32;
+            v_plane[idx] += v.round() as u32;
+            samples[idx] += 1;
+        }
+    }
+
+    let average = |plane: Vec<u32>| -> Vec<u8> {
+        plane
+            .iter()
+            .zip(&samples)
+            .map(|(sum, n)| (sum / n) as u8)
+            .collect()
+    };
+
+    (y_plane, average(u_plane), average(v_plane))
+}
+
+/// Milliseconds between two points on the timeline, rounded so that errors
+/// don't accumulate over many frames.
+fn duration_ms(time: f64, duration: f64) -> u32 {
+    let start = (time * 1000.0).round() as u64;
+    let end = ((time + duration) * 1000.0).round() as u64;
+
+    (end - start) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use imgref::ImgVec;
+    use rgb::{ComponentBytes, RGBA8};
+
+    #[test]
+    fn changed_rect() {
+        let black = RGBA8::new(0, 0, 0, 255);
+        let white = RGBA8::new(255, 255, 255, 255);
+        let a = ImgVec::new(vec![black; 16], 4, 4);
+        let mut b = a.clone();
+
+        assert_eq!(super::changed_rect(a.as_ref(), b.as_ref()), None);
+
+        b[(1usize, 1)] = white;
+        b[(3usize, 2)] = white;
+
+        assert_eq!(
+            super::changed_rect(a.as_ref(), b.as_ref()),
+            Some((1, 1, 3, 2))
+        );
+    }
+
+    #[test]
+    fn resampler() {
+        let mut resampler = super::Resampler::new(10);
+
+        assert_eq!(resampler.repeats(0.0, 0.25), 3);
+        assert_eq!(resampler.repeats(0.25, 0.01), 0);
+        assert_eq!(resampler.repeats(0.26, 0.5), 5);
+        assert_eq!(resampler.repeats(0.76, 3.0), 30);
+    }
+
+    #[test]
+    fn webp() {
+        use super::Encoder;
+
+        let (width, height) = (7, 5);
+        let black = RGBA8::new(0, 0, 0, 255);
+        let first = ImgVec::new(vec![black; width * height], width, height);
+        let mut second = first.clone();
+        second[(3usize, 2usize)] = RGBA8::new(200, 100, 50, 128);
+
+        let mut webp = Vec::new();
+        let mut encoder = super::webp(&mut webp, (width, height), false);
+        encoder.add_frame(first.clone(), 0.0, 0.5).unwrap();
+        encoder.add_frame(second.clone(), 0.5, 1.0).unwrap();
+        encoder.finish().unwrap();
+
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(webp)).unwrap();
+        let mut buf = vec![0; decoder.output_buffer_size().unwrap()];
+
+        assert!(decoder.is_animated());
+        assert!(decoder.has_alpha());
+        assert_eq!(decoder.num_frames(), 2);
+
+        for (expected, delay) in [(first, 500), (second, 1000)] {
+            assert_eq!(decoder.read_frame(&mut buf).unwrap(), delay);
+            assert_eq!(buf, expected.buf().as_bytes());
+        }
+    }
+
+    #[test]
+    fn apng() {
+        use super::Encoder;
+
+        let (width, height) = (7, 5);
+        let black = RGBA8::new(0, 0, 0, 255);
+        let first = ImgVec::new(vec![black; width * height], width, height);
+        let mut second = first.clone();
+        second[(3usize, 2usize)] = RGBA8::new(200, 100, 50, 128);
+
+        let mut apng = Vec::new();
+        let mut encoder = super::apng(&mut apng, (width, height), 2, true);
+        encoder.add_frame(first.clone(), 0.0, 0.5).unwrap();
+        encoder.add_frame(second, 0.5, 100.0).unwrap();
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let mut reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+
+        assert_eq!((control.num_frames, control.num_plays), (2, 1));
+
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        let frame = reader.info().frame_control.unwrap();
+
+        assert_eq!((frame.width, frame.height), (7, 5));
+        assert_eq!((frame.delay_num, frame.delay_den), (500, 1000));
+        assert_eq!(&buf[..width * height * 4], first.buf().as_bytes());
+
+        // only the changed pixel is stored, the delay in centiseconds as it
+        // doesn't fit milliseconds
+        let info = reader.next_frame(&mut buf).unwrap();
+        let frame = reader.info().frame_control.unwrap();
+
+        assert_eq!((frame.x_offset, frame.y_offset), (3, 2));
+        assert_eq!((frame.width, frame.height), (1, 1));
+        assert_eq!((frame.delay_num, frame.delay_den), (10000, 100));
+        assert_eq!(&buf[..info.buffer_size()], &[200, 100, 50, 128]);
+    }
+
+    #[test]
+    fn av1() {
+        use super::Encoder;
+
+        let (width, height) = (32, 16);
+        let black = RGBA8::new(0, 0, 0, 255);
+        let white = RGBA8::new(255, 255, 255, 255);
+
+        let mut ivf = Vec::new();
+        let mut encoder = super::av1(&mut ivf, (width, height), 10).unwrap();
+
+        for (color, time, duration) in [(black, 0.0, 0.2), (white, 0.2, 0.01), (white, 0.21, 0.3)] {
+            let image = ImgVec::new(vec![color; width * height], width, height);
+            encoder.add_frame(image, time, duration).unwrap();
+        }
+
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let (header, mut packets) = ivf.split_at(32);
+        let u16_at = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+
+        assert_eq!(&header[0..4], b"DKIF");
+        assert_eq!(u16_at(6), 32);
+        assert_eq!(&header[8..12], b"AV01");
+        assert_eq!((u16_at(12), u16_at(14)), (32, 16));
+        assert_eq!((u32_at(16), u32_at(20)), (10, 1));
+
+        // frames are repeated to fill 0.2s and 0.3s at 10 fps, the 0.01s one
+        // is dropped
+        let mut frame_numbers = Vec::new();
+
+        while !packets.is_empty() {
+            let size = u32::from_le_bytes(packets[0..4].try_into().unwrap()) as usize;
+            let number = u64::from_le_bytes(packets[4..12].try_into().unwrap());
+            assert!(size > 0);
+            frame_numbers.push(number);
+            packets = &packets[12 + size..];
+        }
+
+        frame_numbers.sort();
+
+        assert_eq!(frame_numbers, [0, 1, 2, 3, 4]);
+    }
+
+    /// Three solid frames lasting 0.25s, 0.01s and 0.5s, which at 10 fps
+    /// are shown for 3, 0 and 5 ticks.
+    fn solid_frames(width: usize, height: usize) -> Vec<(ImgVec<RGBA8>, f64, f64)> {
+        [(255, 0.0, 0.25), (128, 0.25, 0.01), (0, 0.26, 0.5)]
+            .into_iter()
+            .map(|(v, time, duration)| {
+                let pixels = vec![RGBA8::new(v, v, v, 255); width * height];
+                (ImgVec::new(pixels, width, height), time, duration)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn y4m() {
+        use super::Encoder;
+
+        let (width, height) = (4, 3);
+        let mut y4m = Vec::new();
+        let mut encoder = super::y4m(&mut y4m, (width, height), 10);
+
+        for (image, time, duration) in solid_frames(width, height) {
+            encoder.add_frame(image, time, duration).unwrap();
+        }
+
+        encoder.finish().unwrap();
+
+        let header = b"YUV4MPEG2 W4 H3 F10:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED\n";
+
+        assert!(y4m.starts_with(header));
+
+        // 4x3 luma, plus two 2x2 chroma planes
+        let frame_size = b"FRAME\n".len() + 12 + 4 + 4;
+        let frames: Vec<&[u8]> = y4m[header.len()..].chunks(frame_size).collect();
+        let luma: Vec<u8> = frames.iter().map(|frame| frame[6]).collect();
+
+        assert!(frames.iter().all(|frame| frame.starts_with(b"FRAME\n")));
+        assert_eq!(luma, [235, 235, 235, 16, 16, 16, 16, 16]);
+    }
+
+    #[test]
+    fn raw() {
+        use super::Encoder;
+
+        let (width, height) = (4, 3);
+        let mut raw = Vec::new();
+        let mut encoder = super::raw(&mut raw, 10);
+
+        for (image, time, duration) in solid_frames(width, height) {
+            encoder.add_frame(image, time, duration).unwrap();
+        }
+
+        encoder.finish().unwrap();
+
+        let frames: Vec<&[u8]> = raw.chunks(width * height * 4).collect();
+        let values: Vec<u8> = frames.iter().map(|frame| frame[0]).collect();
+
+        assert!(frames.iter().all(|frame| frame.len() == width * height * 4));
+        assert_eq!(values, [255, 255, 255, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn duration_ms() {
+        assert_eq!(super::duration_ms(0.0, 0.0334), 33);
+        assert_eq!(super::duration_ms(0.0334, 0.0334), 34);
+        assert_eq!(super::duration_ms(1.0, 3.0), 3000);
+    }
+}
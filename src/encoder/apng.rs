@@ -0,0 +1,88 @@
+use std::io::Write;
+
+use anyhow::Result;
+use imgref::ImgVec;
+use rgb::{ComponentBytes, RGBA8};
+
+use super::{changed_rect, duration_ms, Encoder};
+
+pub struct ApngEncoder<W: Write> {
+    writer: Option<png::Writer<W>>,
+    output: Option<W>,
+    width: usize,
+    height: usize,
+    frame_count: u32,
+    num_plays: u32,
+    prev_image: Option<ImgVec<RGBA8>>,
+}
+
+impl<W: Write> ApngEncoder<W> {
+    pub fn new(
+        output: W,
+        (width, height): (usize, usize),
+        frame_count: usize,
+        no_loop: bool,
+    ) -> Self {
+        Self {
+            writer: None,
+            output: Some(output),
+            width,
+            height,
+            frame_count: frame_count.max(1) as u32,
+            num_plays: if no_loop { 1 } else { 0 },
+            prev_image: None,
+        }
+    }
+
+    fn writer(&mut self) -> Result<&mut png::Writer<W>> {
+        if let Some(output) = self.output.take() {
+            let mut encoder = png::Encoder::new(output, self.width as u32, self.height as u32);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(self.frame_count, self.num_plays)?;
+            encoder.set_blend_op(png::BlendOp::Source)?;
+            encoder.set_dispose_op(png::DisposeOp::None)?;
+            self.writer = Some(encoder.write_header()?);
+        }
+
+        Ok(self.writer.as_mut().unwrap())
+    }
+}
+
+impl<W: Write> Encoder for ApngEncoder<W> {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()> {
+        let rect = match &self.prev_image {
+            Some(prev) => changed_rect(prev.as_ref(), image.as_ref()).unwrap_or((0, 0, 1, 1)),
+            None => (0, 0, self.width, self.height),
+        };
+
+        let (x, y, width, height) = rect;
+        let ms = duration_ms(time, duration);
+
+        // delay is a u16 fraction, fall back to centiseconds for long frames
+        let (num, den) = if ms <= u16::MAX as u32 {
+            (ms as u16, 1000)
+        } else {
+            ((ms / 10).min(u16::MAX as u32) as u16, 100)
+        };
+
+        let data: Vec<RGBA8> = image.sub_image(x, y, width, height).pixels().collect();
+        let writer = self.writer()?;
+        writer.set_frame_position(0, 0)?;
+        writer.set_frame_dimension(width as u32, height as u32)?;
+        writer.set_frame_position(x as u32, y as u32)?;
+        writer.set_frame_delay(num, den)?;
+        writer.write_image_data(data.as_bytes())?;
+        self.prev_image = Some(image);
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+        }
+
+        Ok(())
+    }
+}
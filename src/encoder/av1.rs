@@ -0,0 +1,147 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::Result;
+use imgref::ImgVec;
+use rav1e::color::{
+    ChromaSampling, ColorDescription, ColorPrimaries, MatrixCoefficients, PixelRange,
+    TransferCharacteristics,
+};
+use rav1e::config::SpeedSettings;
+use rav1e::prelude::{Config, Context, EncoderConfig, EncoderStatus, Frame, Rational};
+use rgb::RGBA8;
+
+use super::{yuv420, Encoder, Resampler};
+
+/// Encodes frames as AV1 and writes them into an IVF container, at a
+/// constant frame rate.
+pub struct Av1Encoder<W: Write> {
+    output: W,
+    context: Context<u8>,
+    width: usize,
+    height: usize,
+    fps: u32,
+    resampler: Resampler,
+}
+
+impl<W: Write> Av1Encoder<W> {
+    pub fn new(output: W, (width, height): (usize, usize), fps: u8) -> Result<Self> {
+        let fps = fps.max(1);
+
+        let config = EncoderConfig {
+            width,
+            height,
+            time_base: Rational::new(1, fps as u64),
+            bit_depth: 8,
+            chroma_sampling: ChromaSampling::Cs420,
+            pixel_range: PixelRange::Limited,
+            color_description: Some(ColorDescription {
+                color_primaries: ColorPrimaries::BT709,
+                transfer_characteristics: TransferCharacteristics::BT709,
+                matrix_coefficients: MatrixCoefficients::BT709,
+            }),
+            speed_settings: SpeedSettings::from_preset(10),
+            ..Default::default()
+        };
+
+        let context = Config::new()
+            .with_encoder_config(config)
+            .with_threads(0)
+            .new_context()?;
+
+        let mut encoder = Self {
+            output,
+            context,
+            width,
+            height,
+            fps: fps as u32,
+            resampler: Resampler::new(fps),
+        };
+
+        encoder.write_ivf_header()?;
+
+        Ok(encoder)
+    }
+
+    fn write_ivf_header(&mut self) -> Result<()> {
+        let mut header = Vec::with_capacity(32);
+        header.extend_from_slice(b"DKIF");
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&32u16.to_le_bytes());
+        header.extend_from_slice(b"AV01");
+        header.extend_from_slice(&(self.width as u16).to_le_bytes());
+        header.extend_from_slice(&(self.height as u16).to_le_bytes());
+        header.extend_from_slice(&self.fps.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        // frame count isn't known upfront, readers don't rely on it
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        self.output.write_all(&header)?;
+
+        Ok(())
+    }
+
+    fn frame(&self, image: &ImgVec<RGBA8>) -> Frame<u8> {
+        let mut frame = self.context.new_frame();
+        let (y_plane, u_plane, v_plane) = yuv420(image.as_ref());
+        let chroma_width = self.width.div_ceil(2);
+
+        frame.planes[0].copy_from_raw_u8(&y_plane, self.width, 1);
+        frame.planes[1].copy_from_raw_u8(&u_plane, chroma_width, 1);
+        frame.planes[2].copy_from_raw_u8(&v_plane, chroma_width, 1);
+
+        for plane in &mut frame.planes {
+            plane.pad(self.width, self.height);
+        }
+
+        frame
+    }
+
+    fn write_packets(&mut self) -> Result<()> {
+        loop {
+            match self.context.receive_packet() {
+                Ok(packet) => {
+                    self.output
+                        .write_all(&(packet.data.len() as u32).to_le_bytes())?;
+                    self.output.write_all(&packet.input_frameno.to_le_bytes())?;
+                    self.output.write_all(&packet.data)?;
+                }
+
+                Err(EncoderStatus::Encoded) => (),
+
+                Err(EncoderStatus::NeedMoreData | EncoderStatus::LimitReached) => {
+                    return Ok(());
+                }
+
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl<W: Write> Encoder for Av1Encoder<W> {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()> {
+        let repeats = self.resampler.repeats(time, duration);
+
+        if repeats == 0 {
+            return Ok(());
+        }
+
+        let frame = Arc::new(self.frame(&image));
+
+        for _ in 0..repeats {
+            self.context.send_frame(frame.clone())?;
+            self.write_packets()?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.context.flush();
+        self.write_packets()?;
+        self.output.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+use anyhow::Result;
+use imgref::ImgVec;
+use rgb::RGBA8;
+use serde::Serialize;
+
+use super::{write_png, Encoder};
+
+pub struct PngSequenceEncoder {
+    path: String,
+    manifest: Manifest,
+    markers: std::vec::IntoIter<(f64, String)>,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    width: usize,
+    height: usize,
+    frames: Vec<ManifestFrame>,
+}
+
+#[derive(Serialize)]
+struct ManifestFrame {
+    index: usize,
+    filename: String,
+    time: f64,
+    duration: f64,
+    markers: Vec<String>,
+}
+
+impl PngSequenceEncoder {
+    pub fn new(path: &str, (width, height): (usize, usize), markers: Vec<(f64, String)>) -> Self {
+        Self {
+            path: path.to_owned(),
+            manifest: Manifest {
+                width,
+                height,
+                frames: Vec::new(),
+            },
+            markers: markers.into_iter(),
+        }
+    }
+}
+
+impl Encoder for PngSequenceEncoder {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()> {
+        let index = self.manifest.frames.len();
+        let filename = format!("{index:06}.png");
+        let file = File::create(format!("{}/{}", self.path, filename))?;
+        write_png(BufWriter::new(file), image.as_ref())?;
+
+        let markers = self
+            .markers
+            .as_slice()
+            .iter()
+            .take_while(|(t, _)| *t < time + duration)
+            .count();
+
+        let markers = self
+            .markers
+            .by_ref()
+            .take(markers)
+            .map(|(_, label)| label)
+            .collect();
+
+        self.manifest.frames.push(ManifestFrame {
+            index,
+            filename,
+            time,
+            duration,
+            markers,
+        });
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(frame) = self.manifest.frames.last_mut() {
+            frame
+                .markers
+                .extend(self.markers.by_ref().map(|(_, label)| label));
+        }
+
+        let json = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(format!("{}/manifest.json", self.path), json)?;
+
+        Ok(())
+    }
+}
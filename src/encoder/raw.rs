@@ -0,0 +1,47 @@
+use std::io::Write;
+
+use anyhow::Result;
+use imgref::ImgVec;
+use rgb::{ComponentBytes, RGBA8};
+
+use super::{Encoder, Resampler};
+
+/// Writes frames as a headerless stream of RGBA pixels, at a constant frame
+/// rate.
+pub struct RawEncoder<W: Write> {
+    output: W,
+    resampler: Resampler,
+}
+
+impl<W: Write> RawEncoder<W> {
+    pub fn new(output: W, fps: u8) -> Self {
+        Self {
+            output,
+            resampler: Resampler::new(fps),
+        }
+    }
+}
+
+impl<W: Write> Encoder for RawEncoder<W> {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()> {
+        let repeats = self.resampler.repeats(time, duration);
+
+        if repeats == 0 {
+            return Ok(());
+        }
+
+        let (buf, ..) = image.into_contiguous_buf();
+
+        for _ in 0..repeats {
+            self.output.write_all(buf.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.output.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::io::Write;
+
+use anyhow::Result;
+use image_webp::{ColorType, WebPEncoder};
+use imgref::ImgVec;
+use rgb::{ComponentBytes, RGBA8};
+
+use super::{changed_rect, duration_ms, Encoder};
+
+pub struct WebpEncoder<W: Write> {
+    output: W,
+    width: usize,
+    height: usize,
+    loop_count: u16,
+    frames: Vec<u8>,
+    prev_image: Option<ImgVec<RGBA8>>,
+    has_alpha: bool,
+}
+
+impl<W: Write> WebpEncoder<W> {
+    pub fn new(output: W, (width, height): (usize, usize), no_loop: bool) -> Self {
+        Self {
+            output,
+            width,
+            height,
+            loop_count: if no_loop { 1 } else { 0 },
+            frames: Vec::new(),
+            prev_image: None,
+            has_alpha: false,
+        }
+    }
+
+    fn encode_vp8l(image: ImgVec<RGBA8>) -> Result<Vec<u8>> {
+        let (buf, width, height) = image.into_contiguous_buf();
+        let mut webp = Vec::new();
+
+        WebPEncoder::new(&mut webp).encode(
+            buf.as_bytes(),
+            width as u32,
+            height as u32,
+            ColorType::Rgba8,
+        )?;
+
+        // strip "RIFF", file size and "WEBP", leaving the complete VP8L chunk
+        Ok(webp.split_off(12))
+    }
+}
+
+impl<W: Write> Encoder for WebpEncoder<W> {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()> {
+        self.has_alpha |= image.pixels().any(|p| p.a < 255);
+
+        let (x, y, width, height) = match &self.prev_image {
+            Some(prev) => {
+                match changed_rect(prev.as_ref(), image.as_ref()) {
+                    // frame offsets must be even
+                    Some((x, y, w, h)) => (x & !1, y & !1, w + (x & 1), h + (y & 1)),
+                    None => (0, 0, 1, 1),
+                }
+            }
+
+            None => (0, 0, self.width, self.height),
+        };
+
+        let sub_image = image.sub_image(x, y, width, height);
+        let sub_image = ImgVec::new(sub_image.pixels().collect(), width, height);
+        let vp8l = Self::encode_vp8l(sub_image)?;
+        let mut anmf = Vec::with_capacity(16 + vp8l.len());
+        anmf.extend_from_slice(&u24(x as u32 / 2));
+        anmf.extend_from_slice(&u24(y as u32 / 2));
+        anmf.extend_from_slice(&u24(width as u32 - 1));
+        anmf.extend_from_slice(&u24(height as u32 - 1));
+        anmf.extend_from_slice(&u24(duration_ms(time, duration).min(0xffffff)));
+        // no blending, no disposal
+        anmf.push(0b10);
+        anmf.extend_from_slice(&vp8l);
+        write_chunk(&mut self.frames, b"ANMF", &anmf)?;
+        self.prev_image = Some(image);
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let mut vp8x = Vec::with_capacity(10);
+        let alpha = if self.has_alpha { 1 << 4 } else { 0 };
+        let animation = 1 << 1;
+        vp8x.extend_from_slice(&[alpha | animation, 0, 0, 0]);
+        vp8x.extend_from_slice(&u24(self.width as u32 - 1));
+        vp8x.extend_from_slice(&u24(self.height as u32 - 1));
+
+        let mut anim = Vec::with_capacity(6);
+        anim.extend_from_slice(&[0, 0, 0, 0]);
+        anim.extend_from_slice(&self.loop_count.to_le_bytes());
+
+        let mut body = Vec::new();
+        body.extend_from_slice(b"WEBP");
+        write_chunk(&mut body, b"VP8X", &vp8x)?;
+        write_chunk(&mut body, b"ANIM", &anim)?;
+
+        self.output.write_all(b"RIFF")?;
+        self.output
+            .write_all(&((body.len() + self.frames.len()) as u32).to_le_bytes())?;
+        self.output.write_all(&body)?;
+        self.output.write_all(&self.frames)?;
+        self.output.flush()?;
+
+        Ok(())
+    }
+}
+
+fn u24(n: u32) -> [u8; 3] {
+    let [a, b, c, _] = n.to_le_bytes();
+
+    [a, b, c]
+}
+
+fn write_chunk<W: Write>(mut w: W, name: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    w.write_all(name)?;
+    w.write_all(&(data.len() as u32).to_le_bytes())?;
+    w.write_all(data)?;
+
+    if data.len() % 2 == 1 {
+        w.write_all(&[0])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use anyhow::Result;
+use imgref::ImgVec;
+use rgb::RGBA8;
+
+use super::{yuv420, Encoder, Resampler};
+
+/// Writes frames as an uncompressed YUV4MPEG2 stream, at a constant frame rate.
+pub struct Y4mEncoder<W: Write> {
+    output: W,
+    width: usize,
+    height: usize,
+    fps: u8,
+    resampler: Resampler,
+    header_written: bool,
+}
+
+impl<W: Write> Y4mEncoder<W> {
+    pub fn new(output: W, (width, height): (usize, usize), fps: u8) -> Self {
+        let fps = fps.max(1);
+
+        Self {
+            output,
+            width,
+            height,
+            fps,
+            resampler: Resampler::new(fps),
+            header_written: false,
+        }
+    }
+}
+
+impl<W: Write> Encoder for Y4mEncoder<W> {
+    fn add_frame(&mut self, image: ImgVec<RGBA8>, time: f64, duration: f64) -> Result<()> {
+        if !self.header_written {
+            writeln!(
+                self.output,
+                "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED",
+                self.width, self.height, self.fps
+            )?;
+
+            self.header_written = true;
+        }
+
+        let repeats = self.resampler.repeats(time, duration);
+
+        if repeats == 0 {
+            return Ok(());
+        }
+
+        let (y_plane, u_plane, v_plane) = yuv420(image.as_ref());
+
+        for _ in 0..repeats {
+            self.output.write_all(b"FRAME\n")?;
+            self.output.write_all(&y_plane)?;
+            self.output.write_all(&u_plane)?;
+            self.output.write_all(&v_plane)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.output.flush()?;
+
+        Ok(())
+    }
+}
@@ -1,23 +1,79 @@
+use std::iter;
+
 use anyhow::Result;
 
-use crate::asciicast::OutputEvent;
+use crate::asciicast::{Event, OutputEvent};
+use crate::speed::SpeedMap;
+
+/// An event placed on the recording timeline.
+pub trait Timed {
+    fn time(&self) -> f64;
+    fn set_time(&mut self, time: f64);
+
+    /// Whether the event changes the terminal. Idle time is measured between
+    /// such events only, other events are shifted along with them.
+    fn is_output(&self) -> bool {
+        true
+    }
+}
+
+impl Timed for OutputEvent {
+    fn time(&self) -> f64 {
+        self.0
+    }
+
+    fn set_time(&mut self, time: f64) {
+        self.0 = time;
+    }
+}
+
+impl Timed for Event {
+    fn time(&self) -> f64 {
+        match self {
+            Event::Output(time, _)
+            | Event::Input(time, _)
+            | Event::Marker(time, _)
+            | Event::Resize(time, ..)
+            | Event::Exit(time, _) => *time,
+        }
+    }
+
+    fn set_time(&mut self, new_time: f64) {
+        match self {
+            Event::Output(time, _)
+            | Event::Input(time, _)
+            | Event::Marker(time, _)
+            | Event::Resize(time, ..)
+            | Event::Exit(time, _) => *time = new_time,
+        }
+    }
+
+    fn is_output(&self) -> bool {
+        matches!(self, Event::Output(..) | Event::Resize(..))
+    }
+}
 
 struct Batch<I>
 where
-    I: Iterator<Item = Result<OutputEvent>>,
+    I: Iterator<Item = Result<Event>>,
 {
     iter: I,
     prev_time: f64,
     prev_data: String,
     max_frame_time: f64,
+    pending: Option<Event>,
 }
 
-impl<I: Iterator<Item = Result<OutputEvent>>> Iterator for Batch<I> {
-    type Item = Result<OutputEvent>;
+impl<I: Iterator<Item = Result<Event>>> Iterator for Batch<I> {
+    type Item = Result<Event>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.take() {
+            return Some(Ok(event));
+        }
+
         match self.iter.next() {
-            Some(Ok((time, data))) => {
+            Some(Ok(Event::Output(time, data))) => {
                 if time - self.prev_time < self.max_frame_time {
                     self.prev_data.push_str(&data);
 
@@ -27,7 +83,7 @@ impl<I: Iterator<Item = Result<OutputEvent>>> Iterator for Batch<I> {
                     self.prev_time = time;
                     let prev_data = std::mem::replace(&mut self.prev_data, data);
 
-                    Some(Ok((prev_time, prev_data)))
+                    Some(Ok(Event::Output(prev_time, prev_data)))
                 } else {
                     self.prev_time = time;
                     self.prev_data = data;
@@ -36,6 +92,27 @@ impl<I: Iterator<Item = Result<OutputEvent>>> Iterator for Batch<I> {
                 }
             }
 
+            // other events end the current batch, and are emitted after it
+            Some(Ok(event)) => {
+                let flushed = (!self.prev_data.is_empty() || self.prev_time == 0.0).then(|| {
+                    let prev_data = std::mem::take(&mut self.prev_data);
+
+                    Event::Output(self.prev_time, prev_data)
+                });
+
+                self.prev_time = event.time();
+
+                match flushed {
+                    Some(output) => {
+                        self.pending = Some(event);
+
+                        Some(Ok(output))
+                    }
+
+                    None => Some(Ok(event)),
+                }
+            }
+
             Some(Err(e)) => Some(Err(e)),
 
             None => {
@@ -43,7 +120,7 @@ impl<I: Iterator<Item = Result<OutputEvent>>> Iterator for Batch<I> {
                     let prev_time = self.prev_time;
                     let prev_data = std::mem::replace(&mut self.prev_data, "".to_owned());
 
-                    Some(Ok((prev_time, prev_data)))
+                    Some(Ok(Event::Output(prev_time, prev_data)))
                 } else {
                     None
                 }
@@ -53,51 +130,272 @@ impl<I: Iterator<Item = Result<OutputEvent>>> Iterator for Batch<I> {
 }
 
 pub fn batch(
-    iter: impl Iterator<Item = Result<OutputEvent>>,
+    iter: impl Iterator<Item = Result<Event>>,
     fps_cap: u8,
-) -> impl Iterator<Item = Result<OutputEvent>> {
+) -> impl Iterator<Item = Result<Event>> {
     Batch {
         iter,
         prev_data: "".to_owned(),
         prev_time: 0.0,
         max_frame_time: 1.0 / (fps_cap as f64),
+        pending: None,
     }
 }
 
-pub fn accelerate(
-    events: impl Iterator<Item = Result<OutputEvent>>,
+pub fn accelerate<E: Timed>(
+    events: impl Iterator<Item = Result<E>>,
     speed: f64,
-) -> impl Iterator<Item = Result<OutputEvent>> {
-    events.map(move |event| event.map(|(time, data)| (time / speed, data)))
+) -> impl Iterator<Item = Result<E>> {
+    events.map(move |event| {
+        event.map(|mut event| {
+            event.set_time(event.time() / speed);
+
+            event
+        })
+    })
 }
 
-pub fn limit_idle_time(
-    events: impl Iterator<Item = Result<OutputEvent>>,
+/// Like `accelerate`, with the speed changing along the timeline.
+pub fn accelerate_map<E: Timed>(
+    events: impl Iterator<Item = Result<E>>,
+    map: SpeedMap,
+) -> impl Iterator<Item = Result<E>> {
+    events.map(move |event| {
+        event.map(|mut event| {
+            event.set_time(map.warp(event.time()));
+
+            event
+        })
+    })
+}
+
+pub fn limit_idle_time<E: Timed>(
+    events: impl Iterator<Item = Result<E>>,
     limit: f64,
-) -> impl Iterator<Item = Result<OutputEvent>> {
+) -> impl Iterator<Item = Result<E>> {
+    compress_idle_time(events, move |delay| delay.min(limit))
+}
+
+/// Shortens pauses between output events, mapping each pause length with
+/// `compress`, which should never make a pause longer.
+pub fn compress_idle_time<E: Timed>(
+    events: impl Iterator<Item = Result<E>>,
+    compress: impl Fn(f64) -> f64,
+) -> impl Iterator<Item = Result<E>> {
     let mut prev_time = 0.0;
     let mut offset = 0.0;
 
     events.map(move |event| {
-        event.map(|(time, data)| {
+        event.map(|mut event| {
+            let time = event.time();
             let delay = time - prev_time;
-            let excess = delay - limit;
+            let excess = (delay - compress(delay)).max(0.0);
 
-            if excess > 0.0 {
+            if event.is_output() {
                 offset += excess;
+                prev_time = time;
+                event.set_time(time - offset);
+            } else {
+                event.set_time(time - offset - excess);
             }
 
-            prev_time = time;
+            event
+        })
+    })
+}
+
+/// Where points in time end up after `compress_idle_time` of the events
+/// with the same `compress`, e.g. the bounds of a time range.
+pub fn compress_idle_time_points(
+    events: &[Event],
+    times: &[f64],
+    compress: impl Fn(f64) -> f64,
+) -> Vec<f64> {
+    let outputs = events
+        .iter()
+        .filter(|event| event.is_output())
+        .map(|event| Probe::Output(event.time()));
+
+    let points = times
+        .iter()
+        .enumerate()
+        .filter(|(_, time)| time.is_finite())
+        .map(|(i, time)| Probe::Point(i, *time));
+
+    let mut probes: Vec<Probe> = outputs.chain(points).collect();
+    probes.sort_by(|a, b| a.time().total_cmp(&b.time()));
+
+    let mut compressed = times.to_vec();
+
+    for probe in compress_idle_time(probes.into_iter().map(Ok), compress).flatten() {
+        if let Probe::Point(i, time) = probe {
+            compressed[i] = time;
+        }
+    }
+
+    compressed
+}
 
-            (time - offset, data)
+/// Stand-in for an event, or a point in time to follow along the timeline.
+enum Probe {
+    Output(f64),
+    Point(usize, f64),
+}
+
+impl Timed for Probe {
+    fn time(&self) -> f64 {
+        match self {
+            Probe::Output(time) | Probe::Point(_, time) => *time,
+        }
+    }
+
+    fn set_time(&mut self, new_time: f64) {
+        match self {
+            Probe::Output(time) | Probe::Point(_, time) => *time = new_time,
+        }
+    }
+
+    fn is_output(&self) -> bool {
+        matches!(self, Probe::Output(_))
+    }
+}
+
+/// Cuts off the timeline before `start` and after `end`. Output (and
+/// resizes) from before the start is squashed at time 0, so that the
+/// terminal begins in the state it had at that point.
+pub fn trim(
+    mut events: impl Iterator<Item = Result<Event>>,
+    start: f64,
+    end: Option<f64>,
+) -> impl Iterator<Item = Result<Event>> {
+    let mut prelude = String::new();
+    let mut pending = Vec::new();
+    let mut done = false;
+
+    iter::from_fn(move || loop {
+        if !pending.is_empty() {
+            return Some(Ok(pending.remove(0)));
+        }
+
+        let next = if done { None } else { events.next() };
+
+        let event = match next {
+            Some(Ok(event)) => event,
+            Some(Err(e)) => return Some(Err(e)),
+            None => return flush(&mut prelude, 0.0).map(Ok),
+        };
+
+        let time = event.time();
+
+        if time < start {
+            match event {
+                Event::Output(_, data) => prelude.push_str(&data),
+
+                Event::Resize(_, cols, rows) => {
+                    pending.extend(flush(&mut prelude, 0.0));
+                    pending.push(Event::Resize(0.0, cols, rows));
+                }
+
+                _ => (),
+            }
+        } else if end.is_some_and(|end| time > end) {
+            done = true;
+        } else {
+            let mut event = event;
+            event.set_time(time - start);
+            pending.extend(flush(&mut prelude, 0.0));
+            pending.push(event);
+        }
+    })
+}
+
+/// Removes time ranges (sorted, not overlapping) from the timeline, moving
+/// everything after each of them earlier. Output (and resizes) from within
+/// a range is squashed at its start, so the terminal state carries over.
+pub fn cut(
+    mut events: impl Iterator<Item = Result<Event>>,
+    ranges: Vec<(f64, f64)>,
+) -> impl Iterator<Item = Result<Event>> {
+    let mut ranges = ranges.into_iter().peekable();
+    let mut offset = 0.0;
+    let mut squashed = String::new();
+    let mut squashed_time = 0.0;
+    let mut pending = Vec::new();
+
+    iter::from_fn(move || loop {
+        if !pending.is_empty() {
+            return Some(Ok(pending.remove(0)));
+        }
+
+        let mut event = match events.next() {
+            Some(Ok(event)) => event,
+            Some(Err(e)) => return Some(Err(e)),
+            None => return flush(&mut squashed, squashed_time).map(Ok),
+        };
+
+        let time = event.time();
+
+        while let Some((start, end)) = ranges.next_if(|(_, end)| *end <= time) {
+            pending.extend(flush(&mut squashed, start - offset));
+            offset += end - start;
+        }
+
+        match ranges.peek() {
+            Some((start, _)) if *start <= time => {
+                squashed_time = start - offset;
+
+                match event {
+                    Event::Output(_, data) => squashed.push_str(&data),
+
+                    Event::Resize(_, cols, rows) => {
+                        pending.extend(flush(&mut squashed, squashed_time));
+                        pending.push(Event::Resize(squashed_time, cols, rows));
+                    }
+
+                    _ => (),
+                }
+            }
+
+            _ => {
+                event.set_time(time - offset);
+                pending.push(event);
+            }
+        }
+    })
+}
+
+/// Freezes the timeline at markers, moving everything after each marker
+/// later by its hold duration, so that the screen at that point stays up for
+/// longer.
+pub fn hold(
+    events: impl Iterator<Item = Result<Event>>,
+    duration: impl Fn(&str) -> f64,
+) -> impl Iterator<Item = Result<Event>> {
+    let mut offset = 0.0;
+
+    events.map(move |event| {
+        event.map(|mut event| {
+            event.set_time(event.time() + offset);
+
+            if let Event::Marker(_, label) = &event {
+                offset += duration(label);
+            }
+
+            event
         })
     })
 }
 
+fn flush(data: &mut String, time: f64) -> Option<Event> {
+    (!data.is_empty()).then(|| Event::Output(time, std::mem::take(data)))
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
 
+    use crate::IdleCurve;
+
     #[test]
     fn accelerate() {
         let stdout = [
@@ -117,48 +415,50 @@ mod tests {
 
     #[test]
     fn batch() {
+        use crate::asciicast::Event::*;
+
         let stdout = [
-            (0.0, "foo".to_owned()),
-            (1.0, "bar".to_owned()),
-            (2.0, "baz".to_owned()),
+            Output(0.0, "foo".to_owned()),
+            Output(1.0, "bar".to_owned()),
+            Output(2.0, "baz".to_owned()),
         ];
 
         let stdout = super::batch(stdout.into_iter().map(Ok), 30)
             .collect::<Result<Vec<_>>>()
             .unwrap();
 
-        assert_eq!(&stdout[0], &(0.0, "foo".to_owned()));
-        assert_eq!(&stdout[1], &(1.0, "bar".to_owned()));
-        assert_eq!(&stdout[2], &(2.0, "baz".to_owned()));
+        assert_eq!(&stdout[0], &Output(0.0, "foo".to_owned()));
+        assert_eq!(&stdout[1], &Output(1.0, "bar".to_owned()));
+        assert_eq!(&stdout[2], &Output(2.0, "baz".to_owned()));
 
         let stdout = [
-            (0.0, "foo".to_owned()),
-            (0.033, "bar".to_owned()),
-            (0.066, "baz".to_owned()),
-            (1.0, "qux".to_owned()),
+            Output(0.0, "foo".to_owned()),
+            Output(0.033, "bar".to_owned()),
+            Output(0.066, "baz".to_owned()),
+            Output(1.0, "qux".to_owned()),
         ];
 
         let stdout = super::batch(stdout.into_iter().map(Ok), 30)
             .collect::<Result<Vec<_>>>()
             .unwrap();
 
-        assert_eq!(&stdout[0], &(0.0, "foobar".to_owned()));
-        assert_eq!(&stdout[1], &(0.066, "baz".to_owned()));
-        assert_eq!(&stdout[2], &(1.0, "qux".to_owned()));
+        assert_eq!(&stdout[0], &Output(0.0, "foobar".to_owned()));
+        assert_eq!(&stdout[1], &Output(0.066, "baz".to_owned()));
+        assert_eq!(&stdout[2], &Output(1.0, "qux".to_owned()));
 
         let stdout = [
-            (0.0, "".to_owned()),
-            (1.0, "foo".to_owned()),
-            (2.0, "bar".to_owned()),
+            Output(0.0, "".to_owned()),
+            Output(1.0, "foo".to_owned()),
+            Output(2.0, "bar".to_owned()),
         ];
 
         let stdout = super::batch(stdout.into_iter().map(Ok), 30)
             .collect::<Result<Vec<_>>>()
             .unwrap();
 
-        assert_eq!(&stdout[0], &(0.0, "".to_owned()));
-        assert_eq!(&stdout[1], &(1.0, "foo".to_owned()));
-        assert_eq!(&stdout[2], &(2.0, "bar".to_owned()));
+        assert_eq!(&stdout[0], &Output(0.0, "".to_owned()));
+        assert_eq!(&stdout[1], &Output(1.0, "foo".to_owned()));
+        assert_eq!(&stdout[2], &Output(2.0, "bar".to_owned()));
     }
 
     #[test]
@@ -181,4 +481,252 @@ mod tests {
         assert_eq!(&stdout[3], &(3.5, "qux".to_owned()));
         assert_eq!(&stdout[4], &(5.5, "quux".to_owned()));
     }
+
+    #[test]
+    fn limit_idle_time_markers() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "foo".to_owned()),
+            Marker(1.0, "a".to_owned()),
+            Marker(5.0, "b".to_owned()),
+            Output(6.0, "bar".to_owned()),
+        ];
+
+        let events = super::limit_idle_time(events.into_iter().map(Ok), 2.0)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(&events[0], &Output(0.0, "foo".to_owned()));
+        assert_eq!(&events[1], &Marker(1.0, "a".to_owned()));
+        assert_eq!(&events[2], &Marker(2.0, "b".to_owned()));
+        assert_eq!(&events[3], &Output(2.0, "bar".to_owned()));
+    }
+
+    fn compress_idle_time(curve: IdleCurve) -> Vec<(f64, String)> {
+        let stdout = [
+            (0.0, "foo".to_owned()),
+            (1.0, "bar".to_owned()),
+            (3.0, "baz".to_owned()),
+            (9.0, "qux".to_owned()),
+            (19.0, "quux".to_owned()),
+        ];
+
+        super::compress_idle_time(stdout.into_iter().map(Ok), |delay| {
+            curve.compress(delay, 2.0, 0.25)
+        })
+        .collect::<Result<Vec<_>>>()
+        .unwrap()
+    }
+
+    fn times(stdout: &[(f64, String)]) -> Vec<f64> {
+        stdout
+            .iter()
+            .map(|(time, _)| (time * 1000.0).round() / 1000.0)
+            .collect()
+    }
+
+    #[test]
+    fn compress_idle_time_clip() {
+        let stdout = compress_idle_time(IdleCurve::Clip);
+
+        assert_eq!(times(&stdout), [0.0, 1.0, 3.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn compress_idle_time_log() {
+        let stdout = compress_idle_time(IdleCurve::Log);
+
+        // 2 * (1 + ln(3)) = 4.197, 2 * (1 + ln(5)) = 5.219
+        assert_eq!(times(&stdout), [0.0, 1.0, 3.0, 7.197, 12.416]);
+    }
+
+    #[test]
+    fn compress_idle_time_sqrt() {
+        let stdout = compress_idle_time(IdleCurve::Sqrt);
+
+        // 2 * sqrt(6 * 2) - 2 = 4.928, 2 * sqrt(10 * 2) - 2 = 6.944
+        assert_eq!(times(&stdout), [0.0, 1.0, 3.0, 7.928, 14.872]);
+    }
+
+    #[test]
+    fn compress_idle_time_scale() {
+        let stdout = compress_idle_time(IdleCurve::Scale);
+
+        // 2 + 4 * 0.25 = 3, 2 + 8 * 0.25 = 4
+        assert_eq!(times(&stdout), [0.0, 1.0, 3.0, 6.0, 10.0]);
+    }
+
+    #[test]
+    fn compress_idle_time_markers() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "foo".to_owned()),
+            Marker(4.0, "a".to_owned()),
+            Output(6.0, "bar".to_owned()),
+        ];
+
+        let events = super::compress_idle_time(events.into_iter().map(Ok), |delay| {
+            IdleCurve::Scale.compress(delay, 2.0, 0.5)
+        })
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+
+        assert_eq!(&events[1], &Marker(3.0, "a".to_owned()));
+        assert_eq!(&events[2], &Output(4.0, "bar".to_owned()));
+    }
+
+    #[test]
+    fn compress_idle_time_points() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "foo".to_owned()),
+            Marker(1.0, "a".to_owned()),
+            Output(6.0, "bar".to_owned()),
+            Output(7.0, "baz".to_owned()),
+        ];
+
+        let times = [0.5, 4.0, 6.0, 6.5, 10.0, f64::INFINITY];
+
+        let times = super::compress_idle_time_points(&events, &times, |delay| {
+            IdleCurve::Clip.compress(delay, 2.0, 0.25)
+        });
+
+        assert_eq!(times, [0.5, 2.0, 2.0, 2.5, 5.0, f64::INFINITY]);
+    }
+
+    #[test]
+    fn batch_resize() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "foo".to_owned()),
+            Output(1.0, "bar".to_owned()),
+            Resize(1.01, 10, 5),
+            Output(1.02, "baz".to_owned()),
+            Output(2.0, "qux".to_owned()),
+        ];
+
+        let events = super::batch(events.into_iter().map(Ok), 30)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(&events[0], &Output(0.0, "foo".to_owned()));
+        assert_eq!(&events[1], &Output(1.0, "bar".to_owned()));
+        assert_eq!(&events[2], &Resize(1.01, 10, 5));
+        assert_eq!(&events[3], &Output(1.01, "baz".to_owned()));
+        assert_eq!(&events[4], &Output(2.0, "qux".to_owned()));
+    }
+
+    #[test]
+    fn trim() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.5, "foo".to_owned()),
+            Marker(1.0, "a".to_owned()),
+            Resize(1.5, 10, 5),
+            Output(2.0, "bar".to_owned()),
+            Output(3.0, "baz".to_owned()),
+            Marker(3.5, "b".to_owned()),
+            Output(4.0, "qux".to_owned()),
+            Exit(5.0, 0),
+        ];
+
+        let events = super::trim(events.into_iter().map(Ok), 2.5, Some(4.0))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                Output(0.0, "foo".to_owned()),
+                Resize(0.0, 10, 5),
+                Output(0.0, "bar".to_owned()),
+                Output(0.5, "baz".to_owned()),
+                Marker(1.0, "b".to_owned()),
+                Output(1.5, "qux".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn trim_past_end() {
+        use crate::asciicast::Event::*;
+
+        let events = [Output(0.5, "foo".to_owned()), Output(1.0, "bar".to_owned())];
+
+        let events = super::trim(events.into_iter().map(Ok), 2.0, None)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(events, [Output(0.0, "foobar".to_owned())]);
+    }
+
+    #[test]
+    fn cut() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.5, "foo".to_owned()),
+            Output(1.0, "bar".to_owned()),
+            Marker(1.5, "a".to_owned()),
+            Resize(2.0, 10, 5),
+            Output(2.5, "baz".to_owned()),
+            Output(3.0, "qux".to_owned()),
+            Output(4.0, "quux".to_owned()),
+            Output(5.5, "corge".to_owned()),
+            Exit(6.0, 0),
+        ];
+
+        let events = super::cut(events.into_iter().map(Ok), vec![(1.0, 3.0), (3.5, 5.0)])
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                Output(0.5, "foo".to_owned()),
+                Output(1.0, "bar".to_owned()),
+                Resize(1.0, 10, 5),
+                Output(1.0, "baz".to_owned()),
+                Output(1.0, "qux".to_owned()),
+                Output(1.5, "quux".to_owned()),
+                Output(2.0, "corge".to_owned()),
+                Exit(2.5, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn hold() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.5, "foo".to_owned()),
+            Marker(1.0, "a".to_owned()),
+            Output(1.0, "bar".to_owned()),
+            Marker(2.0, "hold:3".to_owned()),
+            Output(2.5, "baz".to_owned()),
+        ];
+
+        let duration = |label: &str| if label == "hold:3" { 3.0 } else { 0.5 };
+
+        let events = super::hold(events.into_iter().map(Ok), duration)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                Output(0.5, "foo".to_owned()),
+                Marker(1.0, "a".to_owned()),
+                Output(1.5, "bar".to_owned()),
+                Marker(2.5, "hold:3".to_owned()),
+                Output(6.0, "baz".to_owned()),
+            ]
+        );
+    }
 }
@@ -0,0 +1,139 @@
+use serde_json::{json, Value};
+
+use crate::asciicast::Event;
+use crate::renderer;
+
+const PLAYER_CSS: &str = include_str!("html/player.css");
+const VT_JS: &str = include_str!("html/vt.js");
+const PLAYER_JS: &str = include_str!("html/player.js");
+
+/// Builds a self-contained HTML page with a player for the given events.
+/// The player runs them through a small terminal emulator of its own, so
+/// the page stays small no matter how long the recording is.
+pub fn render(
+    events: &[Event],
+    size: (usize, usize),
+    duration: f64,
+    repeat: bool,
+    title: Option<&str>,
+    settings: &renderer::Settings,
+) -> String {
+    let theme = &settings.theme;
+
+    let events: Vec<Value> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Output(time, data) => Some(json!([time, "o", data])),
+            Event::Resize(time, cols, rows) => Some(json!([time, "r", format!("{cols}x{rows}")])),
+            _ => None,
+        })
+        .collect();
+
+    let data = json!({
+        "cols": size.0,
+        "rows": size.1,
+        "term": {
+            "cols": settings.terminal_size.0,
+            "rows": settings.terminal_size.1,
+        },
+        "fontFamilies": settings.font_families,
+        "fontSize": settings.font_size,
+        "lineHeight": settings.line_height,
+        "theme": {
+            "background": theme.background.to_string(),
+            "foreground": theme.foreground.to_string(),
+            "palette": theme.palette.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+        },
+        "duration": duration,
+        "loop": repeat,
+        "events": events,
+    });
+
+    // "</" can only appear inside JSON strings, where "<\/" means the same
+    let data = data.to_string().replace("</", "<\\/");
+    let title = escape(title.unwrap_or("Terminal recording"));
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+{PLAYER_CSS}</style>
+</head>
+<body>
+<div class="agg-player" tabindex="0"></div>
+<script type="application/json" id="agg-data">{data}</script>
+<script>
+{VT_JS}{PLAYER_JS}</script>
+</body>
+</html>
+"#
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::asciicast::Event;
+    use crate::renderer;
+
+    #[test]
+    fn render() {
+        let settings = renderer::Settings {
+            terminal_size: (20, 5),
+            font_db: fontdb::Database::new(),
+            font_families: vec!["JetBrains Mono".to_owned()],
+            font_size: 14,
+            line_height: 1.4,
+            theme: "000000,ffffff,000000,ff0000,00ff00,ffff00,0000ff,ff00ff,00ffff,ffffff"
+                .parse()
+                .unwrap(),
+            pixel_width: None,
+            pixel_height: None,
+            fill_background: false,
+            title_bar: None,
+        };
+
+        let events = vec![
+            Event::Output(0.0, "".to_owned()),
+            Event::Output(0.5, "\x1b[31m</script>".to_owned()),
+            Event::Resize(1.0, 30, 10),
+            Event::Output(1.5, "done".to_owned()),
+        ];
+
+        let html = super::render(&events, (30, 10), 3.0, false, Some("a <b>"), &settings);
+
+        assert!(html.contains("<title>a &lt;b&gt;</title>"));
+
+        let (_, data) = html.split_once(r#"id="agg-data">"#).unwrap();
+        let (data, _) = data.split_once("</script>").unwrap();
+        let data: Value = serde_json::from_str(data).unwrap();
+
+        assert_eq!(data["cols"], 30);
+        assert_eq!(data["rows"], 10);
+        assert_eq!(data["term"], json!({"cols": 20, "rows": 5}));
+        assert_eq!(data["duration"], 3.0);
+        assert_eq!(data["loop"], false);
+        assert_eq!(data["fontFamilies"], json!(["JetBrains Mono"]));
+        assert_eq!(data["theme"]["palette"][1], "rgb(255,0,0)");
+
+        assert_eq!(
+            data["events"],
+            json!([
+                [0.0, "o", ""],
+                [0.5, "o", "\x1b[31m</script>"],
+                [1.0, "r", "30x10"],
+                [1.5, "o", "done"],
+            ])
+        );
+    }
+}
@@ -0,0 +1,301 @@
+(function () {
+  const BOLD = 1, FAINT = 2, ITALIC = 4, UNDERLINE = 8;
+
+  const data = JSON.parse(document.getElementById("agg-data").textContent);
+  const theme = data.theme;
+  const root = document.querySelector(".agg-player");
+
+  root.style.fontFamily = data.fontFamilies
+    .map((family) => JSON.stringify(family))
+    .concat("monospace")
+    .join(",");
+
+  root.style.fontSize = data.fontSize + "px";
+  root.style.background = theme.background;
+  root.style.color = theme.foreground;
+
+  const screen = document.createElement("pre");
+  screen.className = "agg-screen";
+  screen.style.width = data.cols + "ch";
+  screen.style.lineHeight = data.lineHeight;
+  root.appendChild(screen);
+
+  const rows = [];
+
+  for (let i = 0; i < data.rows; i++) {
+    const row = document.createElement("div");
+    row.className = "agg-row";
+    row.style.height = data.lineHeight + "em";
+    screen.appendChild(row);
+    rows.push(row);
+  }
+
+  const controls = document.createElement("div");
+  controls.className = "agg-controls";
+  const button = document.createElement("button");
+  const seek = document.createElement("input");
+  seek.type = "range";
+  seek.min = 0;
+  seek.max = data.duration;
+  seek.step = 0.01;
+  const clock = document.createElement("span");
+  controls.append(button, seek, clock);
+  root.appendChild(controls);
+
+  function color(c) {
+    if (typeof c !== "number") return c;
+    if (c < 16) return theme.palette[c];
+
+    if (c < 232) {
+      const n = c - 16;
+      const level = (v) => (v > 0 ? v * 40 + 55 : 0);
+      return rgb(level(Math.floor(n / 36) % 6), level(Math.floor(n / 6) % 6), level(n % 6));
+    }
+
+    const v = 8 + 10 * (c - 232);
+    return rgb(v, v, v);
+  }
+
+  function rgb(r, g, b) {
+    return "#" + [r, g, b].map((v) => v.toString(16).padStart(2, "0")).join("");
+  }
+
+  // Splits a line into [col, text, fg, bg, flags, width] runs of cells
+  // sharing the same attributes, leaving out trailing blanks.
+  function segments(line) {
+    const runs = [];
+    let col = 0;
+
+    for (const cell of line) {
+      if (cell.width === 0) continue;
+
+      const pen = cell.pen;
+      let fg = pen.fg;
+      let bg = pen.bg;
+
+      if (pen.bold && typeof fg === "number" && fg < 8) fg += 8;
+      if (pen.blink && typeof bg === "number" && bg < 8) bg += 8;
+
+      if (pen.inverse) {
+        [fg, bg] = [bg === null ? theme.background : bg, fg === null ? theme.foreground : fg];
+      }
+
+      const flags =
+        (pen.bold ? BOLD : 0) |
+        (pen.faint ? FAINT : 0) |
+        (pen.italic ? ITALIC : 0) |
+        (pen.underline ? UNDERLINE : 0);
+
+      const last = runs[runs.length - 1];
+
+      if (
+        last &&
+        last[2] === fg &&
+        last[3] === bg &&
+        last[4] === flags &&
+        cell.width === 1 &&
+        last[5] === Array.from(last[1]).length
+      ) {
+        last[1] += cell.ch;
+        last[5] += 1;
+      } else {
+        runs.push([col, cell.ch, fg, bg, flags, cell.width]);
+      }
+
+      col += cell.width;
+    }
+
+    return runs.filter((run) => {
+      if (run[3] === null && !(run[4] & UNDERLINE)) {
+        const text = run[1].replace(/ +$/, "");
+        run[5] -= run[1].length - text.length;
+        run[1] = text;
+      }
+
+      return run[1] !== "";
+    });
+  }
+
+  function span(col, text, fg, bg, flags, width, inverse) {
+    const el = document.createElement("span");
+    let f = color(fg);
+    let b = color(bg);
+
+    if (inverse) {
+      [f, b] = [b || theme.background, f || theme.foreground];
+    }
+
+    el.textContent = text;
+    el.style.left = col + "ch";
+    el.style.width = width + "ch";
+    if (f) el.style.color = f;
+    if (b) el.style.background = b;
+    if (flags & BOLD) el.style.fontWeight = "bold";
+    if (flags & FAINT) el.style.opacity = 0.5;
+    if (flags & ITALIC) el.style.fontStyle = "italic";
+    if (flags & UNDERLINE) el.style.textDecoration = "underline";
+
+    return el;
+  }
+
+  function drawRow(row, segments, cursorCol) {
+    const spans = [];
+    let cursorDrawn = false;
+
+    for (const [col, text, fg, bg, flags, width] of segments) {
+      const chars = Array.from(text);
+
+      if (cursorCol === null || cursorCol < col || cursorCol >= col + width) {
+        spans.push(span(col, text, fg, bg, flags, width, false));
+      } else if (chars.length !== width) {
+        spans.push(span(col, text, fg, bg, flags, width, true));
+        cursorDrawn = true;
+      } else {
+        const i = cursorCol - col;
+        const before = chars.slice(0, i).join("");
+        const after = chars.slice(i + 1).join("");
+        if (before) spans.push(span(col, before, fg, bg, flags, i, false));
+        spans.push(span(cursorCol, chars[i], fg, bg, flags, 1, true));
+        if (after) spans.push(span(cursorCol + 1, after, fg, bg, flags, width - i - 1, false));
+        cursorDrawn = true;
+      }
+    }
+
+    if (cursorCol !== null && !cursorDrawn) {
+      spans.push(span(cursorCol, " ", null, null, 0, 1, true));
+    }
+
+    row.replaceChildren(...spans);
+  }
+
+  // rows are only redrawn when their contents or the cursor on them change
+  const drawn = rows.map(() => null);
+
+  function draw() {
+    rows.forEach((row, r) => {
+      const line = term.lines[r] || [];
+      const cursorCol = term.cursorVisible && term.y === r ? term.x : null;
+      const runs = segments(line);
+      const key = JSON.stringify([runs, cursorCol]);
+
+      if (key !== drawn[r]) {
+        drawn[r] = key;
+        drawRow(row, runs, cursorCol);
+      }
+    });
+  }
+
+  let term = null;
+  let next = 0;
+  let position = 0;
+
+  // Feeds the terminal with all events up to time t, starting over from
+  // the beginning when going back in time.
+  function advance(t) {
+    if (term === null || t < position) {
+      term = new AggVt(data.term.cols, data.term.rows);
+      next = 0;
+    }
+
+    position = t;
+
+    while (next < data.events.length && data.events[next][0] <= t) {
+      const [, type, payload] = data.events[next++];
+
+      if (type === "o") {
+        term.feed(payload);
+      } else if (type === "r") {
+        const [cols, rows] = payload.split("x").map(Number);
+        term.resize(cols, rows);
+      }
+    }
+  }
+
+  function formatTime(t) {
+    const secs = Math.floor(t);
+    return Math.floor(secs / 60) + ":" + String(secs % 60).padStart(2, "0");
+  }
+
+  let time = 0;
+  let playing = false;
+  let origin = 0;
+
+  function show(t) {
+    time = Math.min(Math.max(t, 0), data.duration);
+    advance(time);
+    draw();
+
+    seek.value = time;
+    clock.textContent = formatTime(time) + " / " + formatTime(data.duration);
+  }
+
+  function tick(now) {
+    if (!playing) return;
+
+    let t = (now - origin) / 1000;
+
+    if (t >= data.duration) {
+      if (data.loop) {
+        origin = now;
+        t = 0;
+      } else {
+        show(data.duration);
+        pause();
+        return;
+      }
+    }
+
+    show(t);
+    requestAnimationFrame(tick);
+  }
+
+  function play() {
+    if (time >= data.duration) time = 0;
+    playing = true;
+    origin = performance.now() - time * 1000;
+    button.textContent = "❚❚";
+    button.title = "Pause";
+    requestAnimationFrame(tick);
+  }
+
+  function pause() {
+    playing = false;
+    button.textContent = "▶";
+    button.title = "Play";
+  }
+
+  function toggle() {
+    if (playing) {
+      pause();
+    } else {
+      play();
+    }
+  }
+
+  function seekTo(t) {
+    show(t);
+    origin = performance.now() - time * 1000;
+  }
+
+  button.addEventListener("click", toggle);
+  seek.addEventListener("input", () => seekTo(parseFloat(seek.value)));
+
+  root.addEventListener("keydown", (e) => {
+    if (e.target === seek && e.key !== " ") return;
+
+    if (e.key === " ") {
+      toggle();
+    } else if (e.key === "ArrowLeft") {
+      seekTo(time - 5);
+    } else if (e.key === "ArrowRight") {
+      seekTo(time + 5);
+    } else {
+      return;
+    }
+
+    e.preventDefault();
+  });
+
+  show(0);
+  play();
+})();
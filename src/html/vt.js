@@ -0,0 +1,765 @@
+// A small terminal emulator for the player, covering the control sequences
+// found in typical recordings: cursor movement, erasing, scrolling regions,
+// SGR attributes, the alternate screen and the DEC line drawing charset.
+const AggVt = (function () {
+  const DEFAULT_PEN = Object.freeze({
+    fg: null,
+    bg: null,
+    bold: false,
+    faint: false,
+    italic: false,
+    underline: false,
+    blink: false,
+    inverse: false,
+  });
+
+  const LINE_DRAWING = {
+    "`": "◆", a: "▒", b: "␉", c: "␌", d: "␍", e: "␊", f: "°", g: "±",
+    h: "␤", i: "␋", j: "┘", k: "┐", l: "┌", m: "└", n: "┼", o: "⎺",
+    p: "⎻", q: "─", r: "⎼", s: "⎽", t: "├", u: "┤", v: "┴", w: "┬",
+    x: "│", y: "≤", z: "≥", "{": "π", "|": "≠", "}": "£", "~": "·",
+  };
+
+  const ZERO_WIDTH = [
+    [0x0300, 0x036f], [0x0483, 0x0489], [0x0591, 0x05bd], [0x0610, 0x061a],
+    [0x064b, 0x065f], [0x200b, 0x200f], [0x20d0, 0x20ff], [0xfe00, 0xfe0f],
+    [0xfe20, 0xfe2f], [0xe0100, 0xe01ef],
+  ];
+
+  const WIDE = [
+    [0x1100, 0x115f], [0x231a, 0x231b], [0x2329, 0x232a], [0x23e9, 0x23ec],
+    [0x23f0, 0x23f0], [0x23f3, 0x23f3], [0x25fd, 0x25fe], [0x2614, 0x2615],
+    [0x2648, 0x2653], [0x267f, 0x267f], [0x2693, 0x2693], [0x26a1, 0x26a1],
+    [0x26aa, 0x26ab], [0x26bd, 0x26be], [0x26c4, 0x26c5], [0x26ce, 0x26ce],
+    [0x26d4, 0x26d4], [0x26ea, 0x26ea], [0x26f2, 0x26f3], [0x26f5, 0x26f5],
+    [0x26fa, 0x26fa], [0x26fd, 0x26fd], [0x2705, 0x2705], [0x270a, 0x270b],
+    [0x2728, 0x2728], [0x274c, 0x274c], [0x274e, 0x274e], [0x2753, 0x2755],
+    [0x2757, 0x2757], [0x2795, 0x2797], [0x27b0, 0x27b0], [0x27bf, 0x27bf],
+    [0x2b1b, 0x2b1c], [0x2b50, 0x2b50], [0x2b55, 0x2b55], [0x2e80, 0x303e],
+    [0x3041, 0x33ff], [0x3400, 0x4dbf], [0x4e00, 0x9fff], [0xa000, 0xa4cf],
+    [0xa960, 0xa97f], [0xac00, 0xd7a3], [0xf900, 0xfaff], [0xfe10, 0xfe19],
+    [0xfe30, 0xfe6f], [0xff00, 0xff60], [0xffe0, 0xffe6], [0x1f004, 0x1f004],
+    [0x1f0cf, 0x1f0cf], [0x1f18e, 0x1f18e], [0x1f191, 0x1f19a],
+    [0x1f200, 0x1f251], [0x1f300, 0x1f64f], [0x1f680, 0x1f6ff],
+    [0x1f7e0, 0x1f7eb], [0x1f900, 0x1f9ff], [0x1fa70, 0x1faff],
+    [0x20000, 0x3fffd],
+  ];
+
+  function inRanges(cp, ranges) {
+    return ranges.some(([from, to]) => cp >= from && cp <= to);
+  }
+
+  function charWidth(cp) {
+    if (inRanges(cp, ZERO_WIDTH)) return 0;
+    if (inRanges(cp, WIDE)) return 2;
+    return 1;
+  }
+
+  function hex(r, g, b) {
+    return "#" + [r, g, b].map((c) => (c & 255).toString(16).padStart(2, "0")).join("");
+  }
+
+  class Terminal {
+    constructor(cols, rows) {
+      this.cols = cols;
+      this.rows = rows;
+      this.reset();
+    }
+
+    reset() {
+      this.pen = DEFAULT_PEN;
+      this.lines = this.blankLines(this.rows);
+      this.primary = null;
+      this.x = 0;
+      this.y = 0;
+      this.wrapPending = false;
+      this.top = 0;
+      this.bottom = this.rows - 1;
+      this.cursorVisible = true;
+      this.autowrap = true;
+      this.insert = false;
+      this.originMode = false;
+      this.charsets = [false, false];
+      this.charset = 0;
+      this.savedCursor = null;
+      this.lastChar = null;
+      this.state = "ground";
+      this.params = "";
+      this.intermediates = "";
+      this.resetTabs();
+    }
+
+    resetTabs() {
+      this.tabs = new Set();
+
+      for (let x = 8; x < this.cols; x += 8) {
+        this.tabs.add(x);
+      }
+    }
+
+    blank() {
+      const pen = this.pen.bg === null ? DEFAULT_PEN : { ...DEFAULT_PEN, bg: this.pen.bg };
+      return { ch: " ", width: 1, pen };
+    }
+
+    blankLine() {
+      return Array.from({ length: this.cols }, () => this.blank());
+    }
+
+    blankLines(n) {
+      return Array.from({ length: n }, () => this.blankLine());
+    }
+
+    feed(data) {
+      for (const ch of data) {
+        this.step(ch, ch.codePointAt(0));
+      }
+    }
+
+    step(ch, cp) {
+      if (cp === 0x18 || cp === 0x1a) {
+        this.state = "ground";
+        return;
+      }
+
+      if (cp === 0x1b) {
+        this.state = "escape";
+        this.intermediates = "";
+        return;
+      }
+
+      switch (this.state) {
+        case "ground":
+          if (cp < 0x20 || cp === 0x7f) {
+            this.control(cp);
+          } else if (cp === 0x9b) {
+            this.startCsi();
+          } else if (cp === 0x9d) {
+            this.state = "osc";
+          } else if (cp >= 0x80 && cp < 0xa0) {
+            // other C1 controls are ignored
+          } else {
+            this.print(ch, cp);
+          }
+          break;
+
+        case "escape":
+          if (cp < 0x20) {
+            this.control(cp);
+          } else if (cp < 0x30) {
+            this.intermediates += ch;
+          } else if (this.intermediates === "" && ch === "[") {
+            this.startCsi();
+          } else if (this.intermediates === "" && ch === "]") {
+            this.state = "osc";
+          } else if (this.intermediates === "" && "PX^_".includes(ch)) {
+            this.state = "string";
+          } else {
+            this.state = "ground";
+            this.escDispatch(ch);
+          }
+          break;
+
+        case "csi":
+          if (cp < 0x20) {
+            this.control(cp);
+          } else if (cp < 0x30) {
+            this.intermediates += ch;
+          } else if (cp < 0x40) {
+            this.params += ch;
+          } else {
+            this.state = "ground";
+            if (cp < 0x7f) this.csiDispatch(ch);
+          }
+          break;
+
+        case "osc":
+          if (cp === 0x07 || cp === 0x9c) this.state = "ground";
+          break;
+
+        case "string":
+          if (cp === 0x9c) this.state = "ground";
+          break;
+      }
+    }
+
+    startCsi() {
+      this.state = "csi";
+      this.params = "";
+      this.intermediates = "";
+    }
+
+    control(cp) {
+      switch (cp) {
+        case 0x08:
+          this.moveTo(this.x - 1, this.y);
+          break;
+        case 0x09:
+          this.tab(1);
+          break;
+        case 0x0a:
+        case 0x0b:
+        case 0x0c:
+          this.lineFeed();
+          break;
+        case 0x0d:
+          this.x = 0;
+          this.wrapPending = false;
+          break;
+        case 0x0e:
+          this.charset = 1;
+          break;
+        case 0x0f:
+          this.charset = 0;
+          break;
+      }
+    }
+
+    print(ch, cp) {
+      if (this.charsets[this.charset] && LINE_DRAWING[ch]) {
+        ch = LINE_DRAWING[ch];
+      }
+
+      const width = charWidth(cp);
+
+      if (width === 0) {
+        const x = this.wrapPending ? this.x : this.x - 1;
+        const line = this.lines[this.y];
+
+        if (x >= 0) {
+          const at = line[x].width === 0 && x > 0 ? x - 1 : x;
+          line[at] = { ...line[at], ch: line[at].ch + ch };
+        }
+
+        return;
+      }
+
+      if (this.wrapPending && this.autowrap) {
+        this.x = 0;
+        this.lineFeed();
+      }
+
+      if (width === 2 && this.x === this.cols - 1) {
+        if (!this.autowrap) return;
+        this.lines[this.y][this.x] = this.blank();
+        this.x = 0;
+        this.lineFeed();
+      }
+
+      const line = this.lines[this.y];
+
+      if (this.insert) {
+        line.splice(this.x, 0, ...Array.from({ length: width }, () => this.blank()));
+        line.length = this.cols;
+      }
+
+      this.clearWide(line, this.x);
+      if (width === 2) this.clearWide(line, this.x + 1);
+
+      line[this.x] = { ch, width, pen: this.pen };
+      if (width === 2) line[this.x + 1] = { ch: "", width: 0, pen: this.pen };
+
+      this.lastChar = ch;
+      this.x += width;
+      this.wrapPending = false;
+
+      if (this.x >= this.cols) {
+        this.x = this.cols - 1;
+        this.wrapPending = this.autowrap;
+      }
+    }
+
+    // Blanks the other half of a wide character about to be overwritten
+    clearWide(line, x) {
+      if (x >= this.cols) return;
+
+      if (line[x].width === 0 && x > 0) {
+        line[x - 1] = this.blank();
+      } else if (line[x].width === 2 && x + 1 < this.cols) {
+        line[x + 1] = this.blank();
+      }
+    }
+
+    lineFeed() {
+      if (this.y === this.bottom) {
+        this.scrollUp(1);
+      } else if (this.y < this.rows - 1) {
+        this.y++;
+      }
+
+      this.wrapPending = false;
+    }
+
+    reverseIndex() {
+      if (this.y === this.top) {
+        this.scrollDown(1);
+      } else if (this.y > 0) {
+        this.y--;
+      }
+
+      this.wrapPending = false;
+    }
+
+    scrollUp(n) {
+      n = Math.min(n, this.bottom - this.top + 1);
+      this.lines.splice(this.top, n);
+      this.lines.splice(this.bottom - n + 1, 0, ...this.blankLines(n));
+    }
+
+    scrollDown(n) {
+      n = Math.min(n, this.bottom - this.top + 1);
+      this.lines.splice(this.bottom - n + 1, n);
+      this.lines.splice(this.top, 0, ...this.blankLines(n));
+    }
+
+    moveTo(x, y) {
+      this.x = Math.min(Math.max(x, 0), this.cols - 1);
+      this.y = Math.min(Math.max(y, 0), this.rows - 1);
+      this.wrapPending = false;
+    }
+
+    tab(n) {
+      for (let i = 0; i < n; i++) {
+        let x = this.x + 1;
+        while (x < this.cols - 1 && !this.tabs.has(x)) x++;
+        this.x = Math.min(x, this.cols - 1);
+      }
+
+      this.wrapPending = false;
+    }
+
+    backTab(n) {
+      for (let i = 0; i < n; i++) {
+        let x = this.x - 1;
+        while (x > 0 && !this.tabs.has(x)) x--;
+        this.x = Math.max(x, 0);
+      }
+
+      this.wrapPending = false;
+    }
+
+    erase(y, from, to) {
+      const line = this.lines[y];
+      from = Math.max(from, 0);
+      to = Math.min(to, this.cols);
+      if (from > 0 && line[from].width === 0) line[from - 1] = this.blank();
+      if (to < this.cols && line[to].width === 0) line[to] = this.blank();
+
+      for (let x = from; x < to; x++) {
+        line[x] = this.blank();
+      }
+    }
+
+    saveCursor() {
+      this.savedCursor = {
+        x: this.x,
+        y: this.y,
+        pen: this.pen,
+        originMode: this.originMode,
+        charsets: [...this.charsets],
+        charset: this.charset,
+      };
+    }
+
+    restoreCursor() {
+      const saved = this.savedCursor || {
+        x: 0,
+        y: 0,
+        pen: DEFAULT_PEN,
+        originMode: false,
+        charsets: [false, false],
+        charset: 0,
+      };
+
+      this.moveTo(saved.x, saved.y);
+      this.pen = saved.pen;
+      this.originMode = saved.originMode;
+      this.charsets = [...saved.charsets];
+      this.charset = saved.charset;
+    }
+
+    escDispatch(ch) {
+      switch (this.intermediates) {
+        case "(":
+        case ")":
+          this.charsets[this.intermediates === "(" ? 0 : 1] = ch === "0";
+          return;
+        case "":
+          break;
+        default:
+          return;
+      }
+
+      switch (ch) {
+        case "7":
+          this.saveCursor();
+          break;
+        case "8":
+          this.restoreCursor();
+          break;
+        case "D":
+          this.lineFeed();
+          break;
+        case "E":
+          this.x = 0;
+          this.lineFeed();
+          break;
+        case "H":
+          this.tabs.add(this.x);
+          break;
+        case "M":
+          this.reverseIndex();
+          break;
+        case "c":
+          this.reset();
+          break;
+      }
+    }
+
+    csiDispatch(final) {
+      const priv = "?<=>".includes(this.params[0] || "x") ? this.params[0] : "";
+      const args = (priv ? this.params.slice(1) : this.params).split(";");
+      const param = (i, dflt) => {
+        const v = parseInt(args[i], 10);
+        return isNaN(v) ? dflt : v;
+      };
+      const count = (i) => Math.max(param(i, 1), 1);
+
+      if (this.intermediates === "!" && final === "p") {
+        this.softReset();
+        return;
+      }
+
+      if (this.intermediates !== "") return;
+
+      if (priv !== "" && !"hl".includes(final)) return;
+
+      const top = this.originMode ? this.top : 0;
+
+      switch (final) {
+        case "@": {
+          const line = this.lines[this.y];
+          this.clearWide(line, this.x);
+          line.splice(this.x, 0, ...Array.from({ length: count(0) }, () => this.blank()));
+          line.length = this.cols;
+          break;
+        }
+        case "A":
+          this.moveTo(this.x, Math.max(this.y - count(0), this.y >= this.top ? this.top : 0));
+          break;
+        case "B":
+        case "e":
+          this.moveTo(this.x, Math.min(this.y + count(0), this.y <= this.bottom ? this.bottom : this.rows - 1));
+          break;
+        case "C":
+        case "a":
+          this.moveTo(this.x + count(0), this.y);
+          break;
+        case "D":
+          this.moveTo(this.x - count(0), this.y);
+          break;
+        case "E":
+          this.moveTo(0, Math.min(this.y + count(0), this.y <= this.bottom ? this.bottom : this.rows - 1));
+          break;
+        case "F":
+          this.moveTo(0, Math.max(this.y - count(0), this.y >= this.top ? this.top : 0));
+          break;
+        case "G":
+        case "`":
+          this.moveTo(count(0) - 1, this.y);
+          break;
+        case "H":
+        case "f":
+          this.moveTo(count(1) - 1, top + count(0) - 1);
+          break;
+        case "I":
+          this.tab(count(0));
+          break;
+        case "J":
+          switch (param(0, 0)) {
+            case 0:
+              this.erase(this.y, this.x, this.cols);
+              for (let y = this.y + 1; y < this.rows; y++) this.erase(y, 0, this.cols);
+              break;
+            case 1:
+              for (let y = 0; y < this.y; y++) this.erase(y, 0, this.cols);
+              this.erase(this.y, 0, this.x + 1);
+              break;
+            case 2:
+            case 3:
+              for (let y = 0; y < this.rows; y++) this.erase(y, 0, this.cols);
+              break;
+          }
+          break;
+        case "K":
+          switch (param(0, 0)) {
+            case 0:
+              this.erase(this.y, this.x, this.cols);
+              break;
+            case 1:
+              this.erase(this.y, 0, this.x + 1);
+              break;
+            case 2:
+              this.erase(this.y, 0, this.cols);
+              break;
+          }
+          break;
+        case "L":
+          if (this.y >= this.top && this.y <= this.bottom) {
+            const n = Math.min(count(0), this.bottom - this.y + 1);
+            this.lines.splice(this.bottom - n + 1, n);
+            this.lines.splice(this.y, 0, ...this.blankLines(n));
+            this.x = 0;
+          }
+          break;
+        case "M":
+          if (this.y >= this.top && this.y <= this.bottom) {
+            const n = Math.min(count(0), this.bottom - this.y + 1);
+            this.lines.splice(this.y, n);
+            this.lines.splice(this.bottom - n + 1, 0, ...this.blankLines(n));
+            this.x = 0;
+          }
+          break;
+        case "P": {
+          const line = this.lines[this.y];
+          this.clearWide(line, this.x);
+          line.splice(this.x, count(0));
+          while (line.length < this.cols) line.push(this.blank());
+          break;
+        }
+        case "S":
+          this.scrollUp(count(0));
+          break;
+        case "T":
+          this.scrollDown(count(0));
+          break;
+        case "X":
+          this.erase(this.y, this.x, this.x + count(0));
+          break;
+        case "Z":
+          this.backTab(count(0));
+          break;
+        case "b":
+          if (this.lastChar !== null) {
+            for (let i = 0; i < count(0); i++) {
+              this.print(this.lastChar, this.lastChar.codePointAt(0));
+            }
+          }
+          break;
+        case "d":
+          this.moveTo(this.x, top + count(0) - 1);
+          break;
+        case "g":
+          if (param(0, 0) === 0) {
+            this.tabs.delete(this.x);
+          } else if (param(0, 0) === 3) {
+            this.tabs.clear();
+          }
+          break;
+        case "h":
+        case "l":
+          args.forEach((_, i) => this.setMode(priv, param(i, 0), final === "h"));
+          break;
+        case "m":
+          this.sgr(args);
+          break;
+        case "r": {
+          const t = count(0) - 1;
+          const b = Math.min(param(1, 0) || this.rows, this.rows) - 1;
+
+          if (t < b) {
+            this.top = t;
+            this.bottom = b;
+            this.moveTo(0, this.originMode ? this.top : 0);
+          }
+          break;
+        }
+        case "s":
+          this.saveCursor();
+          break;
+        case "u":
+          this.restoreCursor();
+          break;
+      }
+    }
+
+    softReset() {
+      this.pen = DEFAULT_PEN;
+      this.cursorVisible = true;
+      this.autowrap = true;
+      this.insert = false;
+      this.originMode = false;
+      this.top = 0;
+      this.bottom = this.rows - 1;
+      this.charsets = [false, false];
+      this.charset = 0;
+      this.savedCursor = null;
+    }
+
+    setMode(priv, mode, on) {
+      if (priv === "") {
+        if (mode === 4) this.insert = on;
+        return;
+      }
+
+      if (priv !== "?") return;
+
+      switch (mode) {
+        case 6:
+          this.originMode = on;
+          this.moveTo(0, on ? this.top : 0);
+          break;
+        case 7:
+          this.autowrap = on;
+          break;
+        case 25:
+          this.cursorVisible = on;
+          break;
+        case 47:
+        case 1047:
+          this.switchScreen(on);
+          break;
+        case 1049:
+          if (on) {
+            this.saveCursor();
+            this.switchScreen(true);
+          } else {
+            this.switchScreen(false);
+            this.restoreCursor();
+          }
+          break;
+      }
+    }
+
+    switchScreen(alternate) {
+      if (alternate && this.primary === null) {
+        this.primary = this.lines;
+        this.lines = this.blankLines(this.rows);
+      } else if (!alternate && this.primary !== null) {
+        this.lines = this.primary;
+        this.primary = null;
+      }
+    }
+
+    sgr(args) {
+      const pen = { ...this.pen };
+
+      const extended = (values) => {
+        if (values[0] === 5) return values[1] & 255;
+        if (values[0] === 2) return hex(values[1], values[2], values[3]);
+        return undefined;
+      };
+
+      for (let i = 0; i < args.length; i++) {
+        if (args[i].includes(":")) {
+          const [code, ...rest] = args[i].split(":").map((v) => parseInt(v, 10) || 0);
+
+          if (code === 38 || code === 48) {
+            // the color space id of "38:2:<id>:r:g:b" is optional
+            const values = rest[0] === 2 && rest.length > 4 ? [2, ...rest.slice(2)] : rest;
+            const color = extended(values);
+            if (color !== undefined) pen[code === 38 ? "fg" : "bg"] = color;
+          } else if (code === 4) {
+            pen.underline = rest[0] !== 0;
+          }
+
+          continue;
+        }
+
+        const code = parseInt(args[i], 10) || 0;
+
+        if (code === 0) {
+          Object.assign(pen, DEFAULT_PEN);
+        } else if (code === 1) {
+          pen.bold = true;
+        } else if (code === 2) {
+          pen.faint = true;
+        } else if (code === 3) {
+          pen.italic = true;
+        } else if (code === 4 || code === 21) {
+          pen.underline = true;
+        } else if (code === 5 || code === 6) {
+          pen.blink = true;
+        } else if (code === 7) {
+          pen.inverse = true;
+        } else if (code === 22) {
+          pen.bold = false;
+          pen.faint = false;
+        } else if (code === 23) {
+          pen.italic = false;
+        } else if (code === 24) {
+          pen.underline = false;
+        } else if (code === 25) {
+          pen.blink = false;
+        } else if (code === 27) {
+          pen.inverse = false;
+        } else if (code >= 30 && code <= 37) {
+          pen.fg = code - 30;
+        } else if (code === 39) {
+          pen.fg = null;
+        } else if (code >= 40 && code <= 47) {
+          pen.bg = code - 40;
+        } else if (code === 49) {
+          pen.bg = null;
+        } else if (code >= 90 && code <= 97) {
+          pen.fg = code - 90 + 8;
+        } else if (code >= 100 && code <= 107) {
+          pen.bg = code - 100 + 8;
+        } else if (code === 38 || code === 48) {
+          const values = args.slice(i + 1, i + 5).map((v) => parseInt(v, 10) || 0);
+          const color = extended(values);
+
+          if (color !== undefined) {
+            pen[code === 38 ? "fg" : "bg"] = color;
+            i += values[0] === 5 ? 2 : 4;
+          }
+        }
+      }
+
+      this.pen = Object.freeze(pen);
+    }
+
+    resize(cols, rows) {
+      if (cols === this.cols && rows === this.rows) return;
+
+      // keep the cursor line on screen when the terminal gets shorter
+      const scroll = Math.max(this.y - (rows - 1), 0);
+      const resizeLines = (lines, scroll) => {
+        lines.splice(0, scroll);
+        lines.length = Math.min(lines.length, rows);
+
+        for (const line of lines) {
+          if (cols < this.cols && line[cols - 1].width === 2) line[cols - 1] = this.blank();
+          line.length = Math.min(line.length, cols);
+        }
+
+        return lines;
+      };
+
+      this.lines = resizeLines(this.lines, scroll);
+      if (this.primary !== null) this.primary = resizeLines(this.primary, 0);
+
+      const oldCols = this.cols;
+      this.cols = cols;
+      this.rows = rows;
+
+      for (const lines of [this.lines, this.primary]) {
+        if (lines === null) continue;
+
+        for (const line of lines) {
+          for (let x = oldCols; x < cols; x++) line.push(this.blank());
+        }
+
+        while (lines.length < rows) lines.push(this.blankLine());
+      }
+
+      this.top = 0;
+      this.bottom = rows - 1;
+      this.moveTo(this.x, this.y - scroll);
+      this.resetTabs();
+    }
+  }
+
+  return Terminal;
+})();
+
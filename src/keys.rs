@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use imgref::ImgVec;
+use log::debug;
+use rgb::{RGB8, RGBA8};
+use tiny_skia::Pixmap;
+
+use crate::renderer::composite;
+use crate::KeysPosition;
+
+const MAX_CAPS: usize = 6;
+const MAX_TEXT_LEN: usize = 16;
+
+pub struct Settings {
+    pub position: KeysPosition,
+    pub timeout: f64,
+    pub fade_out: f64,
+    pub fps: u8,
+    pub font_db: fontdb::Database,
+    pub font_families: Vec<String>,
+    pub font_size: usize,
+    pub foreground: RGB8,
+    pub background: RGB8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Named(String),
+}
+
+/// Shows recent keystrokes as key caps on top of rendered frames.
+pub struct Overlay {
+    strokes: Vec<(f64, Vec<Key>)>,
+    position: KeysPosition,
+    timeout: f64,
+    fade_out: f64,
+    fps: u8,
+    font_families: Vec<String>,
+    font_size: f64,
+    foreground: RGB8,
+    background: RGB8,
+    options: usvg::Options<'static>,
+    cache: HashMap<Vec<String>, Pixmap>,
+}
+
+impl Overlay {
+    pub fn new(inputs: Vec<(f64, String)>, settings: Settings) -> Self {
+        let strokes = inputs
+            .into_iter()
+            .map(|(time, data)| (time, keys(&data)))
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect();
+
+        let options = usvg::Options {
+            fontdb: Arc::new(settings.font_db),
+            ..Default::default()
+        };
+
+        Self {
+            strokes,
+            position: settings.position,
+            timeout: settings.timeout,
+            fade_out: settings.fade_out,
+            fps: settings.fps.max(1),
+            font_families: settings.font_families,
+            font_size: settings.font_size as f64,
+            foreground: settings.foreground,
+            background: settings.background,
+            options,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Points in time at which the overlay changes without the terminal
+    /// necessarily changing, so frames have to be rendered for them.
+    pub fn change_times(&self) -> Vec<f64> {
+        let steps = ((self.fade_out * self.fps as f64).ceil() as usize).max(1);
+        let mut times = Vec::new();
+
+        for (i, (time, _)) in self.strokes.iter().enumerate() {
+            let next_time = self
+                .strokes
+                .get(i + 1)
+                .map(|(t, _)| *t)
+                .unwrap_or(f64::INFINITY);
+
+            times.push(*time);
+
+            for step in 1..=steps {
+                let t = time + self.timeout + self.fade_out * step as f64 / steps as f64;
+
+                if t >= next_time {
+                    break;
+                }
+
+                times.push(t);
+            }
+        }
+
+        times
+    }
+
+    pub fn apply(&mut self, image: &mut ImgVec<RGBA8>, time: f64) {
+        let Some((caps, alpha)) = self.caps(time) else {
+            return;
+        };
+
+        if !self.cache.contains_key(&caps) {
+            match self.render_caps(&caps) {
+                Some(pixmap) => {
+                    self.cache.insert(caps.clone(), pixmap);
+                }
+
+                None => return,
+            }
+        }
+
+        let pixmap = &self.cache[&caps];
+        let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+        let margin = self.font_size as usize;
+
+        let x = match self.position {
+            KeysPosition::TopLeft | KeysPosition::BottomLeft => margin,
+            KeysPosition::Top | KeysPosition::Bottom => image.width().saturating_sub(width) / 2,
+            KeysPosition::TopRight | KeysPosition::BottomRight => {
+                image.width().saturating_sub(width + margin)
+            }
+        };
+
+        let y = match self.position {
+            KeysPosition::TopLeft | KeysPosition::Top | KeysPosition::TopRight => margin,
+            _ => image.height().saturating_sub(height + margin),
+        };
+
+        composite(image, pixmap, (x, y), alpha);
+    }
+
+    /// Key caps to show at the given time, with their opacity.
+    fn caps(&self, time: f64) -> Option<(Vec<String>, f32)> {
+        let last = self
+            .strokes
+            .partition_point(|(t, _)| *t <= time)
+            .checked_sub(1)?;
+        let since = time - self.strokes[last].0;
+
+        if since >= self.timeout + self.fade_out {
+            return None;
+        }
+
+        let alpha = if since <= self.timeout {
+            1.0
+        } else {
+            1.0 - (since - self.timeout) / self.fade_out
+        };
+
+        let mut first = last;
+
+        while first > 0 && self.strokes[first].0 - self.strokes[first - 1].0 <= self.timeout {
+            first -= 1;
+        }
+
+        let keys = self.strokes[first..=last]
+            .iter()
+            .flat_map(|(_, keys)| keys.iter());
+
+        let caps = caps(keys);
+        let skip = caps.len().saturating_sub(MAX_CAPS);
+
+        Some((caps.into_iter().skip(skip).collect(), alpha as f32))
+    }
+
+    fn render_caps(&self, caps: &[String]) -> Option<Pixmap> {
+        let font_size = self.font_size;
+        let padding = font_size * 0.5;
+        let gap = font_size * 0.4;
+        let height = font_size * 1.8;
+        let mut x = 0.0;
+        let mut body = String::new();
+
+        for cap in caps {
+            let width = cap.chars().count() as f64 * font_size * 0.6 + 2.0 * padding;
+
+            let _ = write!(
+                body,
+                r#"<rect x="{x:.1}" width="{width:.1}" height="{height:.1}" rx="{:.1}" style="fill: {}" /><text x="{:.1}" y="{:.1}" text-anchor="middle" xml:space="preserve">{}</text>"#,
+                font_size * 0.3,
+                self.background,
+                x + width / 2.0,
+                height / 2.0 + font_size * 0.35,
+                escape(cap)
+            );
+
+            x += width + gap;
+        }
+
+        let width = (x - gap).max(1.0);
+
+        let svg = format!(
+            r#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height:.1}" font-size="{font_size}px" font-family="{}" font-weight="bold" style="fill: {}">{body}</svg>"#,
+            self.font_families.join(","),
+            self.foreground
+        );
+
+        let tree = usvg::Tree::from_str(&svg, &self.options)
+            .map_err(|e| debug!("invalid key caps svg: {e}"))
+            .ok()?;
+
+        let mut pixmap = Pixmap::new(width.ceil() as u32, height.ceil() as u32)?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+        Some(pixmap)
+    }
+}
+
+/// Merges consecutive typed characters into a single cap and repeated named
+/// keys into a counted one.
+fn caps<'a>(keys: impl Iterator<Item = &'a Key>) -> Vec<String> {
+    enum Cap {
+        Text(String),
+        Named(String, usize),
+    }
+
+    let mut caps: Vec<Cap> = Vec::new();
+
+    for key in keys {
+        match (key, caps.last_mut()) {
+            (Key::Char(ch), Some(Cap::Text(text))) => text.push(*ch),
+            (Key::Char(ch), _) => caps.push(Cap::Text(ch.to_string())),
+            (Key::Named(name), Some(Cap::Named(prev, count))) if prev == name => *count += 1,
+            (Key::Named(name), _) => caps.push(Cap::Named(name.clone(), 1)),
+        }
+    }
+
+    caps.into_iter()
+        .map(|cap| match cap {
+            Cap::Text(text) => {
+                let len = text.chars().count();
+
+                if len > MAX_TEXT_LEN {
+                    let tail: String = text.chars().skip(len - MAX_TEXT_LEN + 1).collect();
+
+                    format!("…{tail}")
+                } else {
+                    text
+                }
+            }
+
+            Cap::Named(name, 1) => name,
+            Cap::Named(name, count) => format!("{name} ×{count}"),
+        })
+        .collect()
+}
+
+/// Splits terminal input into keys, giving control keys and escape
+/// sequences readable names.
+fn keys(input: &str) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let key = match ch {
+            '\x1b' => match chars.next() {
+                None => Key::Named("Esc".to_owned()),
+                Some(intro @ ('[' | 'O')) => {
+                    let mut params = String::new();
+
+                    let Some(last) = chars.find(|c| {
+                        params.push(*c);
+                        ('@'..='~').contains(c)
+                    }) else {
+                        continue;
+                    };
+
+                    params.pop();
+
+                    match escape_sequence_name(intro, &params, last) {
+                        Some(name) => Key::Named(name),
+                        None => {
+                            debug!(
+                                "unknown key sequence: {:?}",
+                                format!("{intro}{params}{last}")
+                            );
+                            continue;
+                        }
+                    }
+                }
+                Some('\x1b') => Key::Named("Esc".to_owned()),
+                Some(ch) => match control_name(ch) {
+                    Some(name) => Key::Named(format!("Alt-{name}")),
+                    None => Key::Named(format!("Alt-{ch}")),
+                },
+            },
+
+            ch => match control_name(ch) {
+                Some(name) => Key::Named(name),
+                None => Key::Char(ch),
+            },
+        };
+
+        keys.push(key);
+    }
+
+    keys
+}
+
+fn control_name(ch: char) -> Option<String> {
+    let name = match ch {
+        '\r' | '\n' => "⏎".to_owned(),
+        '\t' => "Tab".to_owned(),
+        '\x7f' | '\x08' => "⌫".to_owned(),
+        '\0' => "Ctrl-Space".to_owned(),
+        '\x01'..='\x1f' => format!("Ctrl-{}", (ch as u8 + 0x40) as char),
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+fn escape_sequence_name(intro: char, params: &str, last: char) -> Option<String> {
+    let mut params = params.split(';');
+    let first = params.next().unwrap_or("");
+    let modifiers = params
+        .next()
+        .and_then(|m| m.parse::<u8>().ok())
+        .unwrap_or(1);
+
+    let name = match (intro, first, last) {
+        (_, _, 'A') => "↑",
+        (_, _, 'B') => "↓",
+        (_, _, 'C') => "→",
+        (_, _, 'D') => "←",
+        (_, _, 'H') | ('[', "1" | "7", '~') => "Home",
+        (_, _, 'F') | ('[', "4" | "8", '~') => "End",
+        ('[', _, 'Z') => "Shift-Tab",
+        ('O', _, 'P') => "F1",
+        ('O', _, 'Q') => "F2",
+        ('O', _, 'R') => "F3",
+        ('O', _, 'S') => "F4",
+        ('[', "2", '~') => "Ins",
+        ('[', "3", '~') => "Del",
+        ('[', "5", '~') => "PgUp",
+        ('[', "6", '~') => "PgDn",
+        ('[', "15", '~') => "F5",
+        ('[', "17", '~') => "F6",
+        ('[', "18", '~') => "F7",
+        ('[', "19", '~') => "F8",
+        ('[', "20", '~') => "F9",
+        ('[', "21", '~') => "F10",
+        ('[', "23", '~') => "F11",
+        ('[', "24", '~') => "F12",
+        _ => return None,
+    };
+
+    let bits = modifiers.saturating_sub(1);
+    let mut prefix = String::new();
+
+    for (bit, modifier) in [(4, "Ctrl-"), (2, "Alt-"), (1, "Shift-")] {
+        if bits & bit != 0 {
+            prefix.push_str(modifier);
+        }
+    }
+
+    Some(format!("{prefix}{name}"))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Key::{self, *};
+
+    fn named(name: &str) -> Key {
+        Named(name.to_owned())
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(super::keys("ls\r"), [Char('l'), Char('s'), named("⏎")]);
+        assert_eq!(super::keys("\x03\x1b"), [named("Ctrl-C"), named("Esc")]);
+        assert_eq!(
+            super::keys("\x1b[A\x1bOB\x1b[1;5C\x1b[3~"),
+            [named("↑"), named("↓"), named("Ctrl-→"), named("Del")]
+        );
+        assert_eq!(
+            super::keys("\x1bx\t\x7f"),
+            [named("Alt-x"), named("Tab"), named("⌫")]
+        );
+    }
+
+    #[test]
+    fn caps() {
+        let keys = super::keys("echo hi\x7f\x7f\x7f\r\x03");
+
+        assert_eq!(super::caps(keys.iter()), ["echo hi", "⌫ ×3", "⏎", "Ctrl-C"]);
+
+        let keys = super::keys("abcdefghijklmnopqrstuvwxyz");
+
+        assert_eq!(super::caps(keys.iter()), ["…lmnopqrstuvwxyz"]);
+    }
+}
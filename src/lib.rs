@@ -1,13 +1,27 @@
 mod asciicast;
+mod badge;
+mod compression;
+mod concat;
+mod contact_sheet;
+mod edit;
+mod encoder;
 mod events;
 mod fonts;
+mod html;
+mod keys;
+mod pipeline;
 mod renderer;
+mod speed;
 mod theme;
 mod vt;
 
+pub use edit::{CutRange, SpeedSegment, TrimPoint};
+pub use pipeline::IdleCurve;
+
 use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::io::{BufRead, Write};
+use std::str::FromStr;
 use std::{iter, thread, time::Instant};
 
 use anyhow::{anyhow, Result};
@@ -15,6 +29,10 @@ use clap::ArgEnum;
 use log::info;
 
 use crate::asciicast::{Asciicast, Event, Header};
+use crate::edit::edit;
+use crate::encoder::Encoder;
+use crate::events::Timed;
+use crate::pipeline::{hold_segments, idle_time, marker_hold, speed_map, speed_segments, Stage};
 use crate::renderer::Renderer as _;
 
 pub const DEFAULT_FONT_FAMILY: &str =
@@ -26,6 +44,15 @@ pub const DEFAULT_LINE_HEIGHT: f64 = 1.4;
 pub const DEFAULT_NO_LOOP: bool = false;
 pub const DEFAULT_SPEED: f64 = 1.0;
 pub const DEFAULT_IDLE_TIME_LIMIT: f64 = 5.0;
+pub const DEFAULT_KEYS_TIMEOUT: f64 = 1.0;
+pub const DEFAULT_KEYS_FADE_OUT: f64 = 0.5;
+pub const DEFAULT_SHEET_THUMBNAILS: usize = 12;
+pub const DEFAULT_SHEET_COLUMNS: usize = 4;
+pub const DEFAULT_SHEET_SCALE: f64 = 0.5;
+pub const DEFAULT_SHEET_SPACING: usize = 16;
+pub const DEFAULT_CONCAT_GAP: f64 = 0.0;
+pub const DEFAULT_MARKER_HOLD: f64 = 0.0;
+pub const DEFAULT_IDLE_SCALE: f64 = 0.25;
 
 pub struct Config {
     pub width: Option<usize>,
@@ -36,6 +63,8 @@ pub struct Config {
     pub font_size: usize,
     pub fps_cap: u8,
     pub idle_time_limit: Option<f64>,
+    pub idle_curve: IdleCurve,
+    pub idle_scale: f64,
     pub last_frame_duration: f64,
     pub line_height: f64,
     pub no_loop: bool,
@@ -45,6 +74,32 @@ pub struct Config {
     pub theme: Option<Theme>,
     pub show_progress_bar: bool,
     pub fill_background: bool,
+    pub stills_at: Vec<Timestamp>,
+    pub sheet_thumbnails: usize,
+    pub sheet_columns: usize,
+    pub sheet_scale: f64,
+    pub sheet_spacing: usize,
+    pub sheet_markers: bool,
+    pub resize_policy: ResizePolicy,
+    pub keys: bool,
+    pub keys_position: KeysPosition,
+    pub keys_timeout: f64,
+    pub keys_fade_out: f64,
+    pub keys_font_size: Option<usize>,
+    pub keys_color: Option<String>,
+    pub keys_background: Option<String>,
+    pub exit_badge: bool,
+    pub title_bar: bool,
+    pub concat_gap: f64,
+    pub concat_transition: ConcatTransition,
+    pub concat_vt: ConcatVt,
+    pub raw_delay: Option<f64>,
+    pub start: Option<TrimPoint>,
+    pub end: Option<TrimPoint>,
+    pub cuts: Vec<CutRange>,
+    pub speed_map: Vec<SpeedSegment>,
+    pub speed_indicator: bool,
+    pub marker_hold: f64,
 }
 
 impl Default for Config {
@@ -58,6 +113,8 @@ impl Default for Config {
             font_size: DEFAULT_FONT_SIZE,
             fps_cap: DEFAULT_FPS_CAP,
             idle_time_limit: None,
+            idle_curve: Default::default(),
+            idle_scale: DEFAULT_IDLE_SCALE,
             last_frame_duration: DEFAULT_LAST_FRAME_DURATION,
             line_height: DEFAULT_LINE_HEIGHT,
             no_loop: DEFAULT_NO_LOOP,
@@ -67,6 +124,32 @@ impl Default for Config {
             theme: Default::default(),
             show_progress_bar: true,
             fill_background: true,
+            stills_at: vec![Timestamp::End],
+            sheet_thumbnails: DEFAULT_SHEET_THUMBNAILS,
+            sheet_columns: DEFAULT_SHEET_COLUMNS,
+            sheet_scale: DEFAULT_SHEET_SCALE,
+            sheet_spacing: DEFAULT_SHEET_SPACING,
+            sheet_markers: false,
+            resize_policy: Default::default(),
+            keys: false,
+            keys_position: Default::default(),
+            keys_timeout: DEFAULT_KEYS_TIMEOUT,
+            keys_fade_out: DEFAULT_KEYS_FADE_OUT,
+            keys_font_size: None,
+            keys_color: None,
+            keys_background: None,
+            exit_badge: false,
+            title_bar: false,
+            concat_gap: DEFAULT_CONCAT_GAP,
+            concat_transition: Default::default(),
+            concat_vt: Default::default(),
+            raw_delay: None,
+            start: None,
+            end: None,
+            cuts: Vec::new(),
+            speed_map: Vec::new(),
+            speed_indicator: false,
+            marker_hold: DEFAULT_MARKER_HOLD,
         }
     }
 }
@@ -78,14 +161,149 @@ pub enum Renderer {
     Fontdue,
 }
 
+/// How frames are fit onto the image when the terminal was resized during
+/// the recording.
+#[derive(Clone, Debug, ArgEnum, Default)]
+pub enum ResizePolicy {
+    /// Fixed canvas fitting the largest terminal size, content at top-left
+    #[default]
+    Max,
+
+    /// Fixed canvas fitting the largest terminal size, content centered
+    Letterbox,
+
+    /// Canvas of the initial terminal size, content scaled to fit
+    Scale,
+}
+
+/// Where the keystroke overlay is placed.
+#[derive(Clone, Debug, ArgEnum, Default)]
+pub enum KeysPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+/// What is shown between concatenated recordings.
+#[derive(Clone, Debug, PartialEq, ArgEnum, Default)]
+pub enum ConcatTransition {
+    /// Keep the last screen of the previous recording
+    #[default]
+    Hold,
+
+    /// Clear the screen
+    Clear,
+}
+
+/// What happens to the terminal state between concatenated recordings.
+#[derive(Clone, Debug, PartialEq, ArgEnum, Default)]
+pub enum ConcatVt {
+    /// Start each recording with a freshly reset terminal
+    #[default]
+    Reset,
+
+    /// Carry screen contents and modes over to the next recording
+    Preserve,
+}
+
+/// How raw terminal output, which has no timing, is spread over time.
+#[derive(Clone, Debug, PartialEq, ArgEnum)]
+pub enum RawTiming {
+    /// Show one line at a time
+    Lines,
+
+    /// Show one character at a time
+    Chars,
+
+    /// Show one screen at a time, splitting at clear screen sequences
+    Screens,
+}
+
+impl RawTiming {
+    fn default_delay(&self) -> f64 {
+        match self {
+            RawTiming::Lines => 0.05,
+            RawTiming::Chars => 0.01,
+            RawTiming::Screens => 1.0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, ArgEnum, Default)]
 pub enum OutputMode {
     /// Write a single animated GIF of the entire input
     #[default]
     AnimatedGif,
 
+    /// Write a single lossless animated WebP of the entire input
+    AnimatedWebp,
+
+    /// Write a single full-color animated PNG (APNG) of the entire input
+    AnimatedPng,
+
+    /// Write a single self-contained animated SVG of the entire input
+    AnimatedSvg,
+
+    /// Write an AV1 video (in IVF container) of the entire input, at FPS cap frame rate
+    Av1Video,
+
+    /// Write an uncompressed YUV4MPEG2 stream of the entire input, at FPS cap frame rate
+    Y4m,
+
+    /// Write headerless RGBA frames of the entire input, at FPS cap frame rate
+    RawRgba,
+
     /// Write a snapshot PNG of each marker in the input
     SnapshotMarkers,
+
+    /// Write a numbered PNG of every frame plus a JSON manifest with frame timing
+    FrameSequence,
+
+    /// Write a still SVG and PNG at each of the given points in time
+    Stills,
+
+    /// Write the processed timeline as an asciicast v3 file
+    Asciicast,
+
+    /// Write a self-contained HTML page with an interactive player
+    Html,
+
+    /// Write a PNG grid of thumbnails taken at evenly spaced times or at markers
+    ContactSheet,
+}
+
+/// Point on the processed (idle-limited and sped up) timeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Timestamp {
+    Seconds(f64),
+    End,
+}
+
+impl FromStr for Timestamp {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "end" => Ok(Timestamp::End),
+            s => match s.parse::<f64>() {
+                Ok(secs) if secs >= 0.0 => Ok(Timestamp::Seconds(secs)),
+                _ => Err(anyhow!("{s} is not a number of seconds or \"end\"")),
+            },
+        }
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timestamp::Seconds(secs) => write!(f, "{secs}"),
+            Timestamp::End => f.write_str("end"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, ArgEnum, Default)]
@@ -186,40 +404,426 @@ fn renderer_settings(header: &Header, config: &Config) -> Result<renderer::Setti
         pixel_width: config.width,
         pixel_height: config.height,
         fill_background: config.fill_background,
+        title_bar: config.title_bar.then(|| {
+            header
+                .title
+                .clone()
+                .or_else(|| header.command.clone())
+                .unwrap_or_default()
+        }),
     };
     Ok(settings)
 }
 
-pub fn run<I: BufRead, O: Write + Send>(input: I, output: O, config: Config) -> Result<()> {
-    let Asciicast { header, events } = asciicast::open(input)?;
-    let settings = renderer_settings(&header, &config)?;
-    let terminal_size = settings.terminal_size;
-    let mut renderer: Box<dyn renderer::Renderer> = match config.renderer {
-        Renderer::Fontdue => Box::new(renderer::fontdue(settings)),
-        Renderer::Resvg => Box::new(renderer::resvg(settings)),
-    };
+struct Timeline {
+    settings: renderer::Settings,
+    events: Vec<Event>,
+    markers: Vec<(f64, String)>,
+    inputs: Vec<(f64, String)>,
+    /// Largest terminal size, when the terminal was resized during recording
+    max_terminal_size: Option<(usize, usize)>,
+    /// Time and status of the exit event, which ends the timeline
+    exit: Option<(f64, i32)>,
+    /// Window titles set with OSC sequences
+    titles: Vec<(f64, String)>,
+    title: Option<String>,
+    command: Option<String>,
+    /// Segments played at other than normal speed
+    speed_segments: Vec<(f64, f64, f64)>,
+}
 
-    let (width, height) = renderer.pixel_size();
+struct Animation {
+    renderer: Box<dyn renderer::Renderer>,
+    terminal_size: (usize, usize),
+    events: Vec<Event>,
+    markers: Vec<(f64, String)>,
+    keys: Option<keys::Overlay>,
+    /// Extra points in time to render frames at, for the keystroke overlay
+    repeat_times: Vec<f64>,
+    last_frame_duration: f64,
+    badge: Option<badge::Badge>,
+    titles: Vec<(f64, String)>,
+    speed: Option<speed::Indicator>,
+}
 
-    info!("gif dimensions: {}x{}", width, height);
+impl Animation {
+    fn frame_count(&self) -> usize {
+        let frames = vt::frames(self.events.iter().cloned().map(Ok), self.terminal_size);
+
+        vt::dedup(vt::repeat_at(frames, self.repeat_times.clone())).count()
+    }
+}
+
+fn prepare<'a, I: IntoRecording<'a>>(input: I, config: &Config) -> Result<Timeline> {
+    let Recording(Asciicast { header, events }) = input.into_recording()?;
+    let settings = renderer_settings(&header, config)?;
 
     let itl = config
         .idle_time_limit
         .or(header.idle_time_limit)
         .unwrap_or(DEFAULT_IDLE_TIME_LIMIT);
 
-    let events = events.into_iter().filter_map(|event| match event {
-        Ok(Event::Output(time, data)) => Some(Ok((time, data))),
-        Ok(Event::Marker(..)) => None,
-        Err(e) => Some(Err(e)),
-    });
-    let events = iter::once(Ok((0.0, "".to_owned()))).chain(events);
-    let events = events::limit_idle_time(events, itl);
+    let stage = speed_segments(events, config)?;
+    let mut stage = edit(stage, config)?;
+    stage.events = Box::new(iter::once(Ok(Event::Output(0.0, "".to_owned()))).chain(stage.events));
+    let stage = idle_time(stage, itl, config)?;
+
+    let Stage {
+        events,
+        speed_segments,
+    } = speed_map(stage, config);
+
     let events = events::accelerate(events, config.speed);
+    let events = events::hold(events, |label| marker_hold(label, config));
+    let mut markers = Vec::new();
+    let mut inputs = Vec::new();
+    let mut exit = None;
+
+    let events = events.filter_map(|event| match event {
+        _ if exit.is_some() => None,
+        Ok(Event::Exit(time, status)) => {
+            exit = Some((time, status));
+            None
+        }
+        Ok(Event::Marker(time, label)) => {
+            markers.push((time, label));
+            None
+        }
+        Ok(Event::Input(time, data)) => {
+            inputs.push((time, data));
+            None
+        }
+        event => Some(event),
+    });
+
     let events = events::batch(events, config.fps_cap);
-    let events = events.collect::<Vec<_>>();
-    let count = events.len() as u64;
-    let frames = vt::frames(events.into_iter(), terminal_size);
+    let events = events.collect::<Result<Vec<_>>>()?;
+    let max_terminal_size = max_terminal_size(&events, settings.terminal_size);
+    let titles = vt::titles(&events);
+    let speed_segments = hold_segments(speed_segments, &markers, config);
+
+    Ok(Timeline {
+        settings,
+        events,
+        markers,
+        inputs,
+        max_terminal_size,
+        exit,
+        titles,
+        title: header.title,
+        command: header.command,
+        speed_segments,
+    })
+}
+
+fn max_terminal_size(events: &[Event], terminal_size: (usize, usize)) -> Option<(usize, usize)> {
+    let sizes = events.iter().filter_map(|event| match event {
+        Event::Resize(_, cols, rows) => Some((*cols, *rows)),
+        _ => None,
+    });
+
+    let mut resized = false;
+    let mut max_size = terminal_size;
+
+    for (cols, rows) in sizes {
+        resized |= (cols, rows) != terminal_size;
+        max_size = (max_size.0.max(cols), max_size.1.max(rows));
+    }
+
+    resized.then_some(max_size)
+}
+
+fn animation<'a, I: IntoRecording<'a>>(input: I, config: &Config) -> Result<Animation> {
+    let Timeline {
+        settings,
+        events,
+        markers,
+        inputs,
+        max_terminal_size,
+        exit,
+        titles,
+        speed_segments,
+        ..
+    } = prepare(input, config)?;
+
+    let terminal_size = settings.terminal_size;
+
+    let badge_settings = badge::Settings {
+        font_db: settings.font_db.clone(),
+        font_families: settings.font_families.clone(),
+        font_size: config.font_size,
+    };
+
+    let keys = if config.keys {
+        Some(keys_overlay(inputs, &settings, config)?)
+    } else {
+        None
+    };
+
+    let titles = if config.title_bar { titles } else { Vec::new() };
+
+    let speed = (config.speed_indicator && !speed_segments.is_empty())
+        .then(|| speed::Indicator::new(speed_segments, badge_settings.clone()));
+
+    let mut repeat_times = keys
+        .as_ref()
+        .map(keys::Overlay::change_times)
+        .unwrap_or_default();
+
+    repeat_times.extend(titles.iter().map(|(time, _)| *time));
+    repeat_times.extend(speed.iter().flat_map(speed::Indicator::change_times));
+    repeat_times.sort_by(f64::total_cmp);
+    repeat_times.dedup();
+
+    if let Some((end_time, _)) = exit {
+        repeat_times.retain(|time| *time < end_time);
+    }
+
+    let last_time = events
+        .last()
+        .map(Timed::time)
+        .into_iter()
+        .chain(repeat_times.last().copied())
+        .fold(0.0, f64::max);
+
+    let last_frame_duration = last_frame_duration(last_time, exit, config);
+
+    let badge = match exit {
+        Some((_, status)) if config.exit_badge => {
+            info!("exit status: {status}");
+
+            badge::Badge::new(status, badge_settings)
+        }
+
+        _ => None,
+    };
+
+    let renderer = boxed_renderer(settings, max_terminal_size, config);
+
+    Ok(Animation {
+        renderer,
+        terminal_size,
+        events,
+        markers,
+        keys,
+        repeat_times,
+        last_frame_duration,
+        badge,
+        titles,
+        speed,
+    })
+}
+
+/// How long the last frame stays on screen: until the exit event when the
+/// recording has one, otherwise for the configured duration.
+fn last_frame_duration(last_time: f64, exit: Option<(f64, i32)>, config: &Config) -> f64 {
+    match exit {
+        Some((end_time, _)) => (end_time - last_time).max(1.0 / config.fps_cap as f64),
+        None => config.last_frame_duration,
+    }
+}
+
+fn keys_overlay(
+    inputs: Vec<(f64, String)>,
+    settings: &renderer::Settings,
+    config: &Config,
+) -> Result<keys::Overlay> {
+    let color = |value: &Option<String>, default| -> Result<rgb::RGB8> {
+        match value {
+            Some(value) => theme::parse_hex_triplet(value.trim_start_matches('#')),
+            None => Ok(default),
+        }
+    };
+
+    info!("keystrokes: {}", inputs.len());
+
+    let settings = keys::Settings {
+        position: config.keys_position.clone(),
+        timeout: config.keys_timeout,
+        fade_out: config.keys_fade_out,
+        fps: config.fps_cap,
+        font_db: settings.font_db.clone(),
+        font_families: settings.font_families.clone(),
+        font_size: config.keys_font_size.unwrap_or(config.font_size),
+        foreground: color(&config.keys_color, settings.theme.background)?,
+        background: color(&config.keys_background, settings.theme.foreground)?,
+    };
+
+    Ok(keys::Overlay::new(inputs, settings))
+}
+
+fn boxed_renderer(
+    settings: renderer::Settings,
+    max_terminal_size: Option<(usize, usize)>,
+    config: &Config,
+) -> Box<dyn renderer::Renderer> {
+    let kind = config.renderer.clone();
+
+    let new_renderer = move |settings| -> Box<dyn renderer::Renderer> {
+        match kind {
+            Renderer::Fontdue => Box::new(renderer::fontdue(settings)),
+            Renderer::Resvg => Box::new(renderer::resvg(settings)),
+        }
+    };
+
+    match max_terminal_size {
+        Some(max_terminal_size) => {
+            info!("resize policy: {:?}", config.resize_policy);
+
+            Box::new(renderer::resizing(
+                settings,
+                max_terminal_size,
+                config.resize_policy.clone(),
+                new_renderer,
+            ))
+        }
+
+        None => new_renderer(settings),
+    }
+}
+
+/// Screen contents at each of the given points in time, that is the last
+/// frame which appeared at or before it.
+fn frames_at(
+    events: Vec<Event>,
+    terminal_size: (usize, usize),
+    times: &[f64],
+) -> Result<Vec<vt::Frame>> {
+    let mut found: Vec<Option<vt::Frame>> = vec![None; times.len()];
+    let mut prev_frame: Option<vt::Frame> = None;
+
+    for (i, frame) in vt::frames(events.into_iter().map(Ok), terminal_size).enumerate() {
+        let frame = frame?;
+        let time = if i == 0 { 0.0 } else { frame.0 };
+
+        if let Some(prev_frame) = &prev_frame {
+            for (slot, t) in found.iter_mut().zip(times) {
+                if slot.is_none() && *t < time {
+                    *slot = Some(prev_frame.clone());
+                }
+            }
+        }
+
+        prev_frame = Some(frame);
+    }
+
+    let last_frame = prev_frame.ok_or_else(|| anyhow!("the recording has no frames"))?;
+
+    Ok(found
+        .into_iter()
+        .map(|slot| slot.unwrap_or_else(|| last_frame.clone()))
+        .collect())
+}
+
+/// A parsed recording, ready to be passed as input to any of the output
+/// functions.
+pub struct Recording<'a>(Asciicast<'a>);
+
+impl<'a> Recording<'a> {
+    /// Parses an asciicast (v1, v2 or v3) or ttyrec recording, optionally
+    /// xz-compressed.
+    pub fn open<I: BufRead + 'a>(input: I) -> Result<Self> {
+        Ok(Recording(asciicast::open(compression::decompress(input)?)?))
+    }
+}
+
+/// Input of the output functions: either a reader of a recording in any
+/// supported format, or an already parsed [`Recording`].
+pub trait IntoRecording<'a> {
+    fn into_recording(self) -> Result<Recording<'a>>;
+}
+
+impl<'a, I: BufRead + 'a> IntoRecording<'a> for I {
+    fn into_recording(self) -> Result<Recording<'a>> {
+        Recording::open(self)
+    }
+}
+
+impl<'a> IntoRecording<'a> for Recording<'a> {
+    fn into_recording(self) -> Result<Recording<'a>> {
+        Ok(self)
+    }
+}
+
+/// Reads a script(1) typescript and its timing file as a recording, which can
+/// then be passed as input to any of the output functions.
+pub fn import_script<'a, R: BufRead + 'a, T: BufRead>(
+    typescript: R,
+    timing: T,
+    config: &Config,
+) -> Result<Recording<'a>> {
+    let default_size = (
+        config.cols.unwrap_or(80) as u16,
+        config.rows.unwrap_or(24) as u16,
+    );
+
+    let cast = asciicast::open_script(
+        compression::decompress(typescript)?,
+        compression::decompress(timing)?,
+        default_size,
+    )?;
+
+    Ok(Recording(cast))
+}
+
+/// Reads raw terminal output as a recording, timed according to `timing`,
+/// which can then be passed as input to any of the output functions.
+pub fn import_raw<I: BufRead>(
+    input: I,
+    timing: RawTiming,
+    config: &Config,
+) -> Result<Recording<'static>> {
+    let delay = config.raw_delay.unwrap_or_else(|| timing.default_delay());
+    let cast = asciicast::open_raw(compression::decompress(input)?, timing, delay)?;
+
+    Ok(Recording(cast))
+}
+
+/// Splices several recordings into a single one, which can then be passed as
+/// input to any of the output functions.
+pub fn concat<'a, I: BufRead + 'a>(inputs: Vec<I>, config: &Config) -> Result<Recording<'a>> {
+    let casts = inputs
+        .into_iter()
+        .map(|input| Ok(Recording::open(input)?.0))
+        .collect::<Result<Vec<_>>>()?;
+
+    info!("concatenating {} recordings", casts.len());
+
+    let settings = concat::Settings {
+        hold: config.last_frame_duration,
+        gap: config.concat_gap,
+        transition: config.concat_transition.clone(),
+        vt: config.concat_vt.clone(),
+    };
+
+    Ok(Recording(concat::concat(casts, settings)))
+}
+
+pub fn run<'a, I: IntoRecording<'a>, O: Write + Send>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let Animation {
+        mut renderer,
+        terminal_size,
+        events,
+        mut keys,
+        repeat_times,
+        last_frame_duration,
+        badge,
+        titles,
+        speed,
+        ..
+    } = animation(input, &config)?;
+
+    let (width, height) = renderer.pixel_size();
+
+    info!("gif dimensions: {}x{}", width, height);
+
+    let count = (events.len() + repeat_times.len()) as u64;
+    let frames = vt::frames(events.into_iter().map(Ok), terminal_size);
+    let frames = vt::dedup(vt::repeat_at(frames, repeat_times));
 
     let repeat = if config.no_loop {
         gifski::Repeat::Finite(0)
@@ -252,11 +856,32 @@ pub fn run<I: BufRead, O: Write + Send>(input: I, output: O, config: Config) ->
             }
         });
 
-        for (i, frame) in frames.enumerate() {
+        let mut frames = frames.enumerate().peekable();
+        let mut titles = titles.into_iter().peekable();
+
+        while let Some((i, frame)) = frames.next() {
             let (time, lines, cursor) = frame?;
-            let image = renderer.render(&lines, cursor);
             let time = if i == 0 { 0.0 } else { time };
-            collector.add_frame_rgba(i, image, time + config.last_frame_duration)?;
+
+            while let Some((_, title)) = titles.next_if(|(t, _)| *t <= time) {
+                renderer.set_title(&title);
+            }
+
+            let mut image = renderer.render(&lines, cursor);
+
+            if let Some(keys) = &mut keys {
+                keys.apply(&mut image, time);
+            }
+
+            if let Some(speed) = &speed {
+                speed.apply(&mut image, time);
+            }
+
+            if let Some(badge) = badge.as_ref().filter(|_| frames.peek().is_none()) {
+                badge.apply(&mut image);
+            }
+
+            collector.add_frame_rgba(i, image, time + last_frame_duration)?;
         }
 
         drop(collector);
@@ -272,11 +897,317 @@ pub fn run<I: BufRead, O: Write + Send>(input: I, output: O, config: Config) ->
     Ok(())
 }
 
-pub fn write_snapshots<I: BufRead>(input: I, snapshots_path: &str, config: Config) -> Result<()> {
-    let Asciicast { header, events } = asciicast::open(input)?;
+pub fn write_webp<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let animation = animation(input, &config)?;
+    let (width, height) = animation.renderer.pixel_size();
+
+    info!("webp dimensions: {}x{}", width, height);
+
+    let encoder = encoder::webp(output, (width, height), config.no_loop);
+
+    encode(animation, encoder, &config)
+}
+
+pub fn write_apng<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let animation = animation(input, &config)?;
+    let (width, height) = animation.renderer.pixel_size();
+
+    info!("apng dimensions: {}x{}", width, height);
+
+    let encoder = encoder::apng(
+        output,
+        (width, height),
+        animation.frame_count(),
+        config.no_loop,
+    );
+
+    encode(animation, encoder, &config)
+}
+
+pub fn write_video<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let animation = animation(input, &config)?;
+    let (width, height) = animation.renderer.pixel_size();
+
+    info!("video dimensions: {}x{}", width, height);
+
+    let encoder = encoder::av1(output, (width, height), config.fps_cap)?;
+
+    encode(animation, encoder, &config)
+}
+
+pub fn write_frames<'a, I: IntoRecording<'a>>(
+    input: I,
+    frames_path: &str,
+    config: Config,
+) -> Result<()> {
+    let animation = animation(input, &config)?;
+    let (width, height) = animation.renderer.pixel_size();
+
+    info!("frame dimensions: {}x{}", width, height);
+
+    std::fs::create_dir_all(frames_path)?;
+    let encoder = encoder::png_sequence(frames_path, (width, height), animation.markers.clone());
+
+    encode(animation, encoder, &config)
+}
+
+pub fn write_y4m<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let animation = animation(input, &config)?;
+    let (width, height) = animation.renderer.pixel_size();
+
+    info!("y4m dimensions: {}x{}", width, height);
+
+    let encoder = encoder::y4m(output, (width, height), config.fps_cap);
+
+    encode(animation, encoder, &config)
+}
+
+pub fn write_raw<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let animation = animation(input, &config)?;
+    let (width, height) = animation.renderer.pixel_size();
+
+    info!("raw rgba frame dimensions: {}x{}", width, height);
+
+    let encoder = encoder::raw(output, config.fps_cap);
+
+    encode(animation, encoder, &config)
+}
+
+pub fn write_svg<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    mut output: O,
+    config: Config,
+) -> Result<()> {
+    let Timeline {
+        settings,
+        events,
+        max_terminal_size,
+        exit,
+        ..
+    } = prepare(input, &config)?;
+
+    let terminal_size = settings.terminal_size;
+
+    // vector images fit the largest terminal size, smaller screens are
+    // drawn at top-left
+    let renderer = renderer::resvg(renderer::Settings {
+        terminal_size: max_terminal_size.unwrap_or(terminal_size),
+        ..settings
+    });
+
+    let frames = vt::frames(events.into_iter().map(Ok), terminal_size);
+    let frames = vt::dedup(frames).collect::<Result<Vec<_>>>()?;
+
+    info!("svg frames: {}", frames.len());
+
+    let last_time = frames.last().map(|(time, ..)| *time).unwrap_or(0.0);
+    let duration = last_time + last_frame_duration(last_time, exit, &config);
+    let svg = renderer.render_animated_svg(&frames, duration, !config.no_loop);
+    output.write_all(svg.as_bytes())?;
+
+    Ok(())
+}
+
+pub fn write_cast<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    output: O,
+    config: Config,
+) -> Result<()> {
+    let Timeline {
+        settings,
+        events,
+        markers,
+        inputs,
+        exit,
+        title,
+        command,
+        ..
+    } = prepare(input, &config)?;
+
+    let header = Header {
+        term_cols: settings.terminal_size.0 as u16,
+        term_rows: settings.terminal_size.1 as u16,
+        term_theme: Some(settings.theme),
+        idle_time_limit: None,
+        title,
+        command,
+    };
+
+    let mut other_events: Vec<Event> = markers
+        .into_iter()
+        .map(|(time, label)| Event::Marker(time, label))
+        .chain(
+            inputs
+                .into_iter()
+                .map(|(time, data)| Event::Input(time, data)),
+        )
+        .collect();
+
+    other_events.sort_by(|a, b| a.time().total_cmp(&b.time()));
+
+    let mut other_events = other_events.into_iter().peekable();
+    let mut merged = Vec::with_capacity(events.len() + other_events.len());
+
+    for event in events {
+        let time = event.time();
+
+        while let Some(other_event) = other_events.next_if(|e| e.time() <= time) {
+            merged.push(other_event);
+        }
+
+        if !matches!(&event, Event::Output(_, data) if data.is_empty()) {
+            merged.push(event);
+        }
+    }
+
+    merged.extend(other_events);
+    merged.extend(exit.map(|(time, status)| Event::Exit(time, status)));
+
+    info!("cast events: {}", merged.len());
+
+    asciicast::write(output, &header, merged.into_iter().map(Ok))
+}
+
+pub fn write_html<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    mut output: O,
+    config: Config,
+) -> Result<()> {
+    let Timeline {
+        settings,
+        events,
+        max_terminal_size,
+        exit,
+        title,
+        ..
+    } = prepare(input, &config)?;
+
+    info!("html events: {}", events.len());
+
+    let size = max_terminal_size.unwrap_or(settings.terminal_size);
+    let last_time = events.last().map(|event| event.time()).unwrap_or(0.0);
+    let duration = last_time + last_frame_duration(last_time, exit, &config);
+    let html = html::render(
+        &events,
+        size,
+        duration,
+        !config.no_loop,
+        title.as_deref(),
+        &settings,
+    );
+    output.write_all(html.as_bytes())?;
+
+    Ok(())
+}
+
+fn encode<E: Encoder>(animation: Animation, mut encoder: E, config: &Config) -> Result<()> {
+    let Animation {
+        mut renderer,
+        terminal_size,
+        events,
+        mut keys,
+        repeat_times,
+        last_frame_duration,
+        badge,
+        titles,
+        speed,
+        ..
+    } = animation;
+
+    let count = (events.len() + repeat_times.len()) as u64;
+    let frames = vt::frames(events.into_iter().map(Ok), terminal_size);
+    let frames = vt::dedup(vt::repeat_at(frames, repeat_times));
+    let mut pr = config
+        .show_progress_bar
+        .then(|| gifski::progress::ProgressBar::new(count));
+    let start_time = Instant::now();
+    let mut pending = None;
+    let mut titles = titles.into_iter().peekable();
+
+    for (i, frame) in frames.enumerate() {
+        let (time, lines, cursor) = frame?;
+        let time = if i == 0 { 0.0 } else { time };
+
+        while let Some((_, title)) = titles.next_if(|(t, _)| *t <= time) {
+            renderer.set_title(&title);
+        }
+
+        let mut image = renderer.render(&lines, cursor);
+
+        if let Some(keys) = &mut keys {
+            keys.apply(&mut image, time);
+        }
+
+        if let Some(speed) = &speed {
+            speed.apply(&mut image, time);
+        }
+
+        if let Some((prev_time, prev_image)) = pending.replace((time, image)) {
+            encoder.add_frame(prev_image, prev_time, time - prev_time)?;
+
+            if let Some(pr) = &mut pr {
+                pr.inc();
+            }
+        }
+    }
+
+    if let Some((time, mut image)) = pending {
+        if let Some(badge) = &badge {
+            badge.apply(&mut image);
+        }
+
+        encoder.add_frame(image, time, last_frame_duration)?;
+
+        if let Some(pr) = &mut pr {
+            pr.inc();
+        }
+    }
+
+    encoder.finish()?;
+
+    if let Some(mut pr) = pr {
+        pr.finish();
+        println!();
+    }
+
+    info!(
+        "rendering finished in {}s",
+        start_time.elapsed().as_secs_f32()
+    );
+
+    Ok(())
+}
+
+pub fn write_snapshots<'a, I: IntoRecording<'a>>(
+    input: I,
+    snapshots_path: &str,
+    config: Config,
+) -> Result<()> {
+    let Recording(Asciicast { header, events }) = input.into_recording()?;
+    let events = edit(Stage::new(events), &config)?.events;
     let settings = renderer_settings(&header, &config)?;
     let terminal_size = settings.terminal_size;
-    let renderer = renderer::resvg(settings);
+    let mut renderer = renderer::resvg(settings.clone());
 
     let (width, height) = renderer.pixel_size();
 
@@ -294,6 +1225,15 @@ pub fn write_snapshots<I: BufRead>(input: I, snapshots_path: &str, config: Confi
             Event::Output(_time, data) => {
                 vt.feed_str(&data);
             }
+            Event::Resize(_time, cols, rows) => {
+                vt.resize(cols, rows);
+
+                renderer = renderer::resvg(renderer::Settings {
+                    terminal_size: (cols, rows),
+                    ..settings.clone()
+                });
+            }
+            Event::Input(..) | Event::Exit(..) => (),
             Event::Marker(_time, label) => {
                 let label = sanitize(&label);
                 let counter = label_counters.entry(label.clone()).or_insert(0);
@@ -307,7 +1247,10 @@ pub fn write_snapshots<I: BufRead>(input: I, snapshots_path: &str, config: Confi
 
                 let lines = vt.view();
                 let cursor: Option<(usize, usize)> = vt.cursor().into();
-                info!("rendering {}.svg, {}.png, {}.txt", filename, filename, filename);
+                info!(
+                    "rendering {}.svg, {}.png, {}.txt",
+                    filename, filename, filename
+                );
                 use std::fs;
                 let svg = renderer.render_svg(lines, cursor);
                 let pixmap = renderer.render_pixmap(&svg);
@@ -320,8 +1263,258 @@ pub fn write_snapshots<I: BufRead>(input: I, snapshots_path: &str, config: Confi
     Ok(())
 }
 
+pub fn write_stills<'a, I: IntoRecording<'a>>(
+    input: I,
+    stills_path: &str,
+    config: Config,
+) -> Result<()> {
+    let Timeline {
+        settings,
+        events,
+        max_terminal_size,
+        titles,
+        ..
+    } = prepare(input, &config)?;
+
+    let terminal_size = settings.terminal_size;
+    let initial_title = settings.title_bar.clone().unwrap_or_default();
+
+    // vector images fit the largest terminal size, smaller screens are
+    // drawn at top-left
+    let mut renderer = renderer::resvg(renderer::Settings {
+        terminal_size: max_terminal_size.unwrap_or(terminal_size),
+        ..settings
+    });
+    let (width, height) = renderer.pixel_size();
+
+    info!("still dimensions: {}x{}", width, height);
+
+    std::fs::create_dir_all(stills_path)?;
+
+    let times: Vec<f64> = config
+        .stills_at
+        .iter()
+        .map(|timestamp| match timestamp {
+            Timestamp::Seconds(secs) => *secs,
+            Timestamp::End => f64::INFINITY,
+        })
+        .collect();
+
+    let frames = frames_at(events, terminal_size, &times)?;
+
+    for ((timestamp, time), (_, lines, cursor)) in config.stills_at.iter().zip(times).zip(frames) {
+        renderer.set_title(title_at(&titles, time).unwrap_or(&initial_title));
+        let filename = format!("{stills_path}/{timestamp}");
+        info!("rendering {}.svg, {}.png", filename, filename);
+        let svg = renderer.render_svg(&lines, cursor);
+        let pixmap = renderer.render_pixmap(&svg);
+        std::fs::write(format!("{}.svg", filename), &svg)?;
+        pixmap.save_png(format!("{}.png", filename))?;
+    }
+
+    Ok(())
+}
+
+pub fn write_contact_sheet<'a, I: IntoRecording<'a>, O: Write>(
+    input: I,
+    mut output: O,
+    config: Config,
+) -> Result<()> {
+    let Timeline {
+        settings,
+        events,
+        markers,
+        max_terminal_size,
+        exit,
+        titles,
+        ..
+    } = prepare(input, &config)?;
+
+    let terminal_size = settings.terminal_size;
+    let initial_title = settings.title_bar.clone().unwrap_or_default();
+
+    let sheet_settings = contact_sheet::Settings {
+        columns: config.sheet_columns,
+        scale: config.sheet_scale,
+        spacing: config.sheet_spacing,
+        font_db: settings.font_db.clone(),
+        font_families: settings.font_families.clone(),
+        font_size: config.font_size,
+        theme: settings.theme.clone(),
+    };
+
+    let mut renderer = boxed_renderer(settings, max_terminal_size, &config);
+
+    let points: Vec<(f64, String)> = if config.sheet_markers && !markers.is_empty() {
+        markers
+            .into_iter()
+            .map(|(time, label)| {
+                let time_label = contact_sheet::format_time(time);
+
+                if label.is_empty() {
+                    (time, time_label)
+                } else {
+                    (time, format!("{time_label} {label}"))
+                }
+            })
+            .collect()
+    } else {
+        let duration = exit
+            .map(|(time, _)| time)
+            .or_else(|| events.last().map(Timed::time))
+            .unwrap_or(0.0);
+        let count = config.sheet_thumbnails.max(1);
+
+        (0..count)
+            .map(|i| {
+                let time = if count == 1 {
+                    duration
+                } else {
+                    duration * i as f64 / (count - 1) as f64
+                };
+
+                (time, contact_sheet::format_time(time))
+            })
+            .collect()
+    };
+
+    info!("contact sheet thumbnails: {}", points.len());
+
+    let times: Vec<f64> = points.iter().map(|(time, _)| *time).collect();
+    let frames = frames_at(events, terminal_size, &times)?;
+
+    let thumbnails = points
+        .into_iter()
+        .zip(frames)
+        .map(|((time, label), (_, lines, cursor))| {
+            renderer.set_title(title_at(&titles, time).unwrap_or(&initial_title));
+
+            (label, renderer.render(&lines, cursor))
+        })
+        .collect();
+
+    let png = contact_sheet::render(thumbnails, sheet_settings)?;
+    output.write_all(&png)?;
+
+    Ok(())
+}
+
+/// The last window title set at or before the given time.
+fn title_at(titles: &[(f64, String)], time: f64) -> Option<&str> {
+    let i = titles.partition_point(|(t, _)| *t <= time);
+
+    i.checked_sub(1).map(|i| titles[i].1.as_str())
+}
+
 fn sanitize(s: &str) -> String {
     s.chars()
         .filter(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' | ' ' | '.'))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::asciicast::Event;
+    use crate::Config;
+
+    #[test]
+    fn frames_at() {
+        let events = vec![
+            Event::Output(0.0, "".to_owned()),
+            Event::Output(1.0, "a".to_owned()),
+            Event::Output(2.0, "b".to_owned()),
+        ];
+
+        let times = [0.0, 0.5, 1.0, 1.5, 2.5, f64::INFINITY];
+        let frames = super::frames_at(events, (5, 1), &times).unwrap();
+
+        let screens: Vec<String> = frames
+            .iter()
+            .map(|(_, lines, _)| lines[0].text().trim_end().to_owned())
+            .collect();
+
+        // a frame is taken from its start time on, the last one is kept
+        // until the end
+        assert_eq!(screens, ["", "", "a", "a", "ab", "ab"]);
+        assert_eq!(frames[2].2, Some((1, 0)));
+        assert!(super::frames_at(Vec::new(), (5, 1), &times).is_err());
+    }
+
+    #[test]
+    fn last_frame_duration() {
+        let config = Config {
+            fps_cap: 20,
+            last_frame_duration: 3.0,
+            ..Default::default()
+        };
+
+        // the last frame stays up until the exit, for at least one frame
+        assert_eq!(super::last_frame_duration(2.0, None, &config), 3.0);
+        assert_eq!(
+            super::last_frame_duration(2.0, Some((4.5, 0)), &config),
+            2.5
+        );
+        assert_eq!(
+            super::last_frame_duration(2.0, Some((2.0, 1)), &config),
+            0.05
+        );
+    }
+
+    #[test]
+    fn write_frames_manifest() {
+        let cast = r#"{"version": 2, "width": 10, "height": 2}
+[0.5, "o", "a"]
+[1.0, "m", "first"]
+[1.0, "m", "second"]
+[1.5, "o", "b"]
+[4.0, "m", "last"]
+"#;
+
+        let path = std::env::temp_dir().join(format!("agg-frames-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let config = Config {
+            show_progress_bar: false,
+            last_frame_duration: 2.0,
+            ..Default::default()
+        };
+
+        super::write_frames(cast.as_bytes(), path, config).unwrap();
+
+        let manifest = std::fs::read_to_string(format!("{path}/manifest.json")).unwrap();
+        let manifest: Value = serde_json::from_str(&manifest).unwrap();
+        let frames = manifest["frames"].as_array().unwrap();
+
+        for frame in frames {
+            let filename = frame["filename"].as_str().unwrap();
+            assert!(std::path::Path::new(path).join(filename).is_file());
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+
+        let frames: Vec<Value> = frames
+            .iter()
+            .map(|frame| {
+                json!([
+                    frame["filename"],
+                    frame["time"],
+                    frame["duration"],
+                    frame["markers"]
+                ])
+            })
+            .collect();
+
+        // markers land on the frame shown at their time, the ones past the
+        // last frame on the last one
+        assert_eq!(
+            frames,
+            [
+                json!(["000000.png", 0.0, 0.5, []]),
+                json!(["000001.png", 0.5, 1.0, ["first", "second"]]),
+                json!(["000002.png", 1.5, 2.0, ["last"]]),
+            ]
+        );
+    }
+}
@@ -2,10 +2,22 @@ use anyhow::{anyhow, Result};
 use clap::{ArgAction, ArgEnum, Parser};
 use reqwest::header;
 use std::io;
-use std::{fs::File, io::BufReader, iter};
+use std::io::{BufReader, BufWriter, Write};
+use std::{fs::File, iter};
 
 static USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
+static CONTENT_TYPES: [&str; 8] = [
+    "application/x-asciicast",
+    "application/json",
+    "application/octet-stream",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+    "application/x-zstd",
+    "application/x-xz",
+];
+
 #[derive(Clone)]
 pub struct Theme(agg::Theme);
 
@@ -45,17 +57,115 @@ impl clap::builder::TypedValueParser for ThemeValueParser {
 }
 
 #[derive(Parser)]
-#[clap(author, version, about, long_about = None)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    override_usage = "agg [OPTIONS] <INPUT_FILENAME_OR_URL>... <OUTPUT_FILENAME>"
+)]
 struct Cli {
-    /// asciicast path/filename or URL
-    input_filename_or_url: String,
+    /// asciicast path/filename or URL (several are concatenated), followed by output path/filename, or - for stdout
+    #[clap(required = true, min_values = 2, multiple_occurrences = true)]
+    filenames: Vec<String>,
+
+    /// Select output type [default: animated-gif, stills with --at, or y4m when writing to -]
+    #[clap(long, arg_enum)]
+    mode: Option<agg::OutputMode>,
+
+    /// Select how frames are fit when the terminal was resized during recording
+    #[clap(long, arg_enum, default_value_t = agg::ResizePolicy::default())]
+    resize_policy: agg::ResizePolicy,
+
+    /// Show keystrokes from recorded input events as key caps
+    #[clap(long)]
+    keys: bool,
+
+    /// Select keystroke overlay position
+    #[clap(long, arg_enum, default_value_t = agg::KeysPosition::default())]
+    keys_position: agg::KeysPosition,
 
-    /// Output path/filename
-    output_filename: String,
+    /// Seconds key caps stay visible after the last keystroke
+    #[clap(long, default_value_t = agg::DEFAULT_KEYS_TIMEOUT)]
+    keys_timeout: f64,
 
-    /// Whether to write an animated GIF or snapshot PNGs at markers
-    #[clap(long, arg_enum, default_value_t = agg::OutputMode::default())]
-    mode: agg::OutputMode,
+    /// Seconds key caps take to fade out
+    #[clap(long, default_value_t = agg::DEFAULT_KEYS_FADE_OUT)]
+    keys_fade_out: f64,
+
+    /// Specify key caps font size (in pixels) [default: font size]
+    #[clap(long)]
+    keys_font_size: Option<usize>,
+
+    /// Specify key caps text color (hex triplet) [default: theme background]
+    #[clap(long)]
+    keys_color: Option<String>,
+
+    /// Specify key caps background color (hex triplet) [default: theme foreground]
+    #[clap(long)]
+    keys_background: Option<String>,
+
+    /// Show the exit status of the recorded command on the final frame
+    #[clap(long)]
+    exit_badge: bool,
+
+    /// Draw a window title bar with the recording title, or the one set by the program
+    #[clap(long)]
+    title_bar: bool,
+
+    /// Pause between concatenated recordings (in seconds), after the final screen of each is held for --last-frame-duration
+    #[clap(long, default_value_t = agg::DEFAULT_CONCAT_GAP)]
+    concat_gap: f64,
+
+    /// Select what is shown between concatenated recordings
+    #[clap(long, arg_enum, default_value_t = agg::ConcatTransition::default())]
+    concat_transition: agg::ConcatTransition,
+
+    /// Select whether terminal state is reset or preserved between concatenated recordings
+    #[clap(long, arg_enum, default_value_t = agg::ConcatVt::default())]
+    concat_vt: agg::ConcatVt,
+
+    /// Read the input as a script(1) typescript, timed by this timing file
+    #[clap(long, value_name = "FILENAME")]
+    timing: Option<String>,
+
+    /// Read the input as raw terminal output with no timing, shown in parts of this size
+    #[clap(long, arg_enum, value_name = "PART", conflicts_with = "timing")]
+    raw: Option<agg::RawTiming>,
+
+    /// Delay between parts of raw input (in seconds) [default: 0.05 for lines, 0.01 for chars, 1 for screens]
+    #[clap(long)]
+    raw_delay: Option<f64>,
+
+    /// Start at this point in time (seconds or marker label), earlier output still sets up the screen
+    #[clap(long, value_name = "SECS|MARKER")]
+    start: Option<agg::TrimPoint>,
+
+    /// End at this point in time (seconds or marker label)
+    #[clap(long, value_name = "SECS|MARKER")]
+    end: Option<agg::TrimPoint>,
+
+    /// Cut out a time range (START-END, in seconds or marker labels), its output still updates the screen
+    #[clap(
+        long,
+        value_name = "RANGE",
+        multiple_occurrences = true,
+        value_delimiter = ','
+    )]
+    cut: Vec<agg::CutRange>,
+
+    /// Play parts of the recording at other speeds (START-END:SPEED, in seconds of recording time like --start, --end and --cut, or marker:LABEL), e.g. 10-45:8x,marker:deploy-:1.5x
+    #[clap(
+        long,
+        value_name = "SEGMENTS",
+        multiple_occurrences = true,
+        value_delimiter = ','
+    )]
+    speed_map: Vec<agg::SpeedSegment>,
+
+    /// Show the playback speed during sped up (or slowed down) parts of the speed map
+    #[clap(long)]
+    speed_indicator: bool,
 
     /// Select frame rendering backend
     #[clap(long, arg_enum, default_value_t = agg::Renderer::default())]
@@ -93,6 +203,14 @@ struct Cli {
     #[clap(long)]
     idle_time_limit: Option<f64>,
 
+    /// Select how pauses longer than the idle time limit are shortened
+    #[clap(long, arg_enum, value_name = "CURVE", default_value_t = agg::IdleCurve::default())]
+    idle_curve: agg::IdleCurve,
+
+    /// Set the factor for the part of pauses above the idle time limit, with --idle-curve scale
+    #[clap(long, value_name = "FACTOR", value_parser = parse_idle_scale, default_value_t = agg::DEFAULT_IDLE_SCALE)]
+    idle_scale: f64,
+
     /// Set FPS cap
     #[clap(long, default_value_t = agg::DEFAULT_FPS_CAP)]
     fps_cap: u8,
@@ -101,6 +219,10 @@ struct Cli {
     #[clap(long, default_value_t = agg::DEFAULT_LAST_FRAME_DURATION)]
     last_frame_duration: f64,
 
+    /// Hold the frame at each marker for this many seconds (markers labeled hold:SECS hold for SECS)
+    #[clap(long, value_name = "SECS", default_value_t = agg::DEFAULT_MARKER_HOLD)]
+    marker_hold: f64,
+
     /// Override terminal width (number of columns)
     #[clap(long)]
     cols: Option<usize>,
@@ -128,6 +250,38 @@ struct Cli {
     /// Disable filling images with the background color
     #[clap(long)]
     transparent_background: bool,
+
+    /// Points in time (seconds or "end") to write stills at [default: end]
+    #[clap(long, value_delimiter = ',')]
+    at: Vec<agg::Timestamp>,
+
+    /// Number of evenly spaced thumbnails on the contact sheet
+    #[clap(long, default_value_t = agg::DEFAULT_SHEET_THUMBNAILS)]
+    sheet_thumbnails: usize,
+
+    /// Number of columns on the contact sheet
+    #[clap(long, default_value_t = agg::DEFAULT_SHEET_COLUMNS)]
+    sheet_columns: usize,
+
+    /// Scale of contact sheet thumbnails relative to full frame size
+    #[clap(long, default_value_t = agg::DEFAULT_SHEET_SCALE)]
+    sheet_scale: f64,
+
+    /// Spacing between contact sheet thumbnails (in pixels)
+    #[clap(long, default_value_t = agg::DEFAULT_SHEET_SPACING)]
+    sheet_spacing: usize,
+
+    /// Take contact sheet thumbnails at markers instead of evenly spaced times
+    #[clap(long)]
+    sheet_markers: bool,
+}
+
+fn parse_idle_scale(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(scale) if scale >= 0.0 => Ok(scale),
+        Ok(_) => Err("must not be negative".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 fn download(url: &str) -> Result<impl io::Read> {
@@ -140,9 +294,7 @@ fn download(url: &str) -> Result<impl io::Read> {
         .get(url)
         .header(
             header::ACCEPT,
-            header::HeaderValue::from_static(
-                "application/x-asciicast,application/json,application/octet-stream",
-            ),
+            header::HeaderValue::from_str(&CONTENT_TYPES.join(","))?,
         )
         .build()?;
 
@@ -154,16 +306,23 @@ fn download(url: &str) -> Result<impl io::Read> {
         .and_then(|hv| hv.to_str().ok())
         .ok_or_else(|| anyhow!("unknown content type".to_owned()))?;
 
-    if ct != "application/x-asciicast"
-        && ct != "application/json"
-        && ct != "application/octet-stream"
-    {
+    if !supported_content_type(ct) {
         return Err(anyhow!(format!("{ct} is not supported")));
     }
 
     Ok(Box::new(response))
 }
 
+/// Tells whether a downloaded recording can be read, ignoring parameters
+/// like charset. The compression formats are detected by their magic bytes.
+fn supported_content_type(ct: &str) -> bool {
+    let mime = ct.split(';').next().unwrap_or_default().trim();
+
+    CONTENT_TYPES
+        .iter()
+        .any(|supported| mime.eq_ignore_ascii_case(supported))
+}
+
 fn reader(path: &str) -> Result<Box<dyn io::Read>> {
     if path == "-" {
         Ok(Box::new(io::stdin()))
@@ -174,8 +333,46 @@ fn reader(path: &str) -> Result<Box<dyn io::Read>> {
     }
 }
 
+fn output_mode(cli: &Cli, output_filename: &str) -> agg::OutputMode {
+    if !cli.at.is_empty() {
+        return agg::OutputMode::Stills;
+    }
+
+    if output_filename == "-" {
+        return agg::OutputMode::Y4m;
+    }
+
+    agg::OutputMode::default()
+}
+
+fn create_output<F>(output_filename: &str, f: F) -> Result<()>
+where
+    F: FnOnce(&mut (dyn Write + Send)) -> Result<()>,
+{
+    if output_filename == "-" {
+        let mut writer = BufWriter::new(io::stdout());
+        f(&mut writer)?;
+        writer.flush()?;
+
+        return Ok(());
+    }
+
+    let mut output = File::create(output_filename)?;
+
+    match f(&mut output) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            std::fs::remove_file(output_filename)?;
+            Err(err)
+        }
+    }
+}
+
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+
+    // clap makes sure there are at least two
+    let output_filename = cli.filenames.pop().unwrap();
 
     let log_level = match cli.verbose {
         0 => "error",
@@ -188,6 +385,11 @@ fn main() -> Result<()> {
         .format_timestamp(None)
         .init();
 
+    let mode = cli
+        .mode
+        .clone()
+        .unwrap_or_else(|| output_mode(&cli, &output_filename));
+
     let config = agg::Config {
         width: cli.width,
         height: cli.height,
@@ -197,6 +399,8 @@ fn main() -> Result<()> {
         font_size: cli.font_size,
         fps_cap: cli.fps_cap,
         idle_time_limit: cli.idle_time_limit,
+        idle_curve: cli.idle_curve,
+        idle_scale: cli.idle_scale,
         last_frame_duration: cli.last_frame_duration,
         line_height: cli.line_height,
         no_loop: cli.no_loop,
@@ -204,28 +408,163 @@ fn main() -> Result<()> {
         rows: cli.rows,
         speed: cli.speed,
         theme: cli.theme.map(|theme| theme.0),
-        show_progress_bar: !cli.quiet,
+        show_progress_bar: !cli.quiet && output_filename != "-",
         fill_background: !cli.transparent_background,
+        stills_at: if cli.at.is_empty() {
+            vec![agg::Timestamp::End]
+        } else {
+            cli.at
+        },
+        sheet_thumbnails: cli.sheet_thumbnails,
+        sheet_columns: cli.sheet_columns,
+        sheet_scale: cli.sheet_scale,
+        sheet_spacing: cli.sheet_spacing,
+        sheet_markers: cli.sheet_markers,
+        resize_policy: cli.resize_policy,
+        keys: cli.keys,
+        keys_position: cli.keys_position,
+        keys_timeout: cli.keys_timeout,
+        keys_fade_out: cli.keys_fade_out,
+        keys_font_size: cli.keys_font_size,
+        keys_color: cli.keys_color,
+        keys_background: cli.keys_background,
+        exit_badge: cli.exit_badge,
+        title_bar: cli.title_bar,
+        concat_gap: cli.concat_gap,
+        concat_transition: cli.concat_transition,
+        concat_vt: cli.concat_vt,
+        raw_delay: cli.raw_delay,
+        start: cli.start,
+        end: cli.end,
+        cuts: cli.cut,
+        speed_map: cli.speed_map,
+        speed_indicator: cli.speed_indicator,
+        marker_hold: cli.marker_hold,
     };
 
-    let input = BufReader::new(reader(&cli.input_filename_or_url)?);
+    let input = match (cli.filenames.as_slice(), &cli.timing) {
+        ([path], Some(timing)) => {
+            let typescript = BufReader::new(reader(path)?);
+            let timing = BufReader::new(reader(timing)?);
 
-    match cli.mode {
-        agg::OutputMode::AnimatedGif => {
-            let mut output = File::create(&cli.output_filename)?;
-
-            match agg::run(input, &mut output, config) {
-                Ok(()) => (),
-                Err(err) => {
-                    std::fs::remove_file(&cli.output_filename)?;
-                    return Err(err);
-                }
+            agg::import_script(typescript, timing, &config)?
+        }
+
+        (_, Some(_)) => return Err(anyhow!("--timing requires a single typescript input")),
+
+        ([path], None) => match cli.raw {
+            Some(timing) => {
+                let input = BufReader::new(reader(path)?);
+
+                agg::import_raw(input, timing, &config)?
             }
+
+            None => agg::Recording::open(BufReader::new(reader(path)?))?,
+        },
+
+        (_, None) if cli.raw.is_some() => return Err(anyhow!("--raw requires a single input")),
+
+        (paths, None) => {
+            let inputs = paths
+                .iter()
+                .map(|path| Ok(BufReader::new(reader(path)?)))
+                .collect::<Result<Vec<_>>>()?;
+
+            agg::concat(inputs, &config)?
+        }
+    };
+
+    match mode {
+        agg::OutputMode::AnimatedGif => {
+            create_output(&output_filename, |output| agg::run(input, output, config))?;
+        }
+        agg::OutputMode::AnimatedWebp => {
+            create_output(&output_filename, |output| {
+                agg::write_webp(input, output, config)
+            })?;
+        }
+        agg::OutputMode::AnimatedPng => {
+            create_output(&output_filename, |output| {
+                agg::write_apng(input, output, config)
+            })?;
+        }
+        agg::OutputMode::AnimatedSvg => {
+            create_output(&output_filename, |output| {
+                agg::write_svg(input, output, config)
+            })?;
+        }
+        agg::OutputMode::Av1Video => {
+            create_output(&output_filename, |output| {
+                agg::write_video(input, output, config)
+            })?;
+        }
+        agg::OutputMode::Y4m => {
+            create_output(&output_filename, |output| {
+                agg::write_y4m(input, output, config)
+            })?;
+        }
+        agg::OutputMode::RawRgba => {
+            create_output(&output_filename, |output| {
+                agg::write_raw(input, output, config)
+            })?;
         }
         agg::OutputMode::SnapshotMarkers => {
-            agg::write_snapshots(input, &cli.output_filename, config)?;
+            agg::write_snapshots(input, &output_filename, config)?;
+        }
+        agg::OutputMode::FrameSequence => {
+            agg::write_frames(input, &output_filename, config)?;
+        }
+        agg::OutputMode::Stills => {
+            agg::write_stills(input, &output_filename, config)?;
+        }
+        agg::OutputMode::Asciicast => {
+            create_output(&output_filename, |output| {
+                agg::write_cast(input, output, config)
+            })?;
+        }
+        agg::OutputMode::Html => {
+            create_output(&output_filename, |output| {
+                agg::write_html(input, output, config)
+            })?;
+        }
+        agg::OutputMode::ContactSheet => {
+            create_output(&output_filename, |output| {
+                agg::write_contact_sheet(input, output, config)
+            })?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn supported_content_type() {
+        assert!(super::supported_content_type("application/x-asciicast"));
+        assert!(super::supported_content_type(
+            "application/json; charset=utf-8"
+        ));
+        assert!(super::supported_content_type("application/gzip"));
+        assert!(super::supported_content_type("application/zstd"));
+        assert!(super::supported_content_type("application/x-xz"));
+        assert!(!super::supported_content_type("text/html"));
+    }
+
+    #[test]
+    fn idle_scale() {
+        use clap::Parser;
+
+        let parse = |scale: &str| {
+            let arg = format!("--idle-scale={scale}");
+
+            super::Cli::try_parse_from(["agg", &arg, "in.cast", "out.gif"])
+                .map(|cli| cli.idle_scale)
+        };
+
+        assert_eq!(parse("0.25").unwrap(), 0.25);
+        assert_eq!(parse("0").unwrap(), 0.0);
+        assert!(parse("-0.5").is_err());
+        assert!(parse("x").is_err());
+    }
+}
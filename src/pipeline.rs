@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Result};
+use clap::ArgEnum;
+use log::info;
+
+use crate::asciicast::Event;
+use crate::{events, speed, Config};
+
+/// How pauses longer than the idle time limit are shortened.
+#[derive(Clone, Debug, ArgEnum, Default)]
+pub enum IdleCurve {
+    /// Cut every pause down to the limit
+    #[default]
+    Clip,
+
+    /// Grow past the limit logarithmically with the pause length
+    Log,
+
+    /// Grow past the limit with the square root of the pause length
+    Sqrt,
+
+    /// Scale the part of the pause above the limit by --idle-scale
+    Scale,
+}
+
+impl IdleCurve {
+    /// Length of a pause of `delay` seconds after compression. The log and
+    /// sqrt curves start off at the limit with a slope of 1, so that pauses
+    /// just above it are barely shortened, and long ones still differ.
+    pub fn compress(&self, delay: f64, limit: f64, scale: f64) -> f64 {
+        if delay <= limit {
+            return delay;
+        }
+
+        if limit <= 0.0 {
+            return limit.max(0.0);
+        }
+
+        match self {
+            IdleCurve::Clip => limit,
+            IdleCurve::Log => limit * (1.0 + (delay / limit).ln()),
+            IdleCurve::Sqrt => 2.0 * (delay * limit).sqrt() - limit,
+            IdleCurve::Scale => limit + (delay - limit) * scale,
+        }
+    }
+}
+
+/// Events on their way through the processing steps, along with the
+/// segments of their timeline played at other than normal speed, as
+/// (start, end, speed).
+pub struct Stage<'a> {
+    pub events: Box<dyn Iterator<Item = Result<Event>> + 'a>,
+    pub speed_segments: Vec<(f64, f64, f64)>,
+}
+
+impl<'a> Stage<'a> {
+    pub fn new(events: Box<dyn Iterator<Item = Result<Event>> + 'a>) -> Self {
+        Self {
+            events,
+            speed_segments: Vec::new(),
+        }
+    }
+}
+
+/// Resolves --speed-map segments, in recording time (like --start, --end
+/// and --cut).
+pub fn speed_segments<'a>(
+    events: Box<dyn Iterator<Item = Result<Event>> + 'a>,
+    config: &Config,
+) -> Result<Stage<'a>> {
+    if config.speed_map.is_empty() {
+        return Ok(Stage::new(events));
+    }
+
+    // markers can be anywhere in the recording
+    let events = events.collect::<Result<Vec<_>>>()?;
+    let mut segments = Vec::new();
+
+    for segment in &config.speed_map {
+        let (start, end) = segment.resolve(&events)?;
+        segments.push((start, end, segment.speed));
+    }
+
+    segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    if let Some(w) = segments.windows(2).find(|w| w[1].0 < w[0].1) {
+        return Err(anyhow!(
+            "speed segments {}s - {}s and {}s - {}s overlap",
+            w[0].0,
+            w[0].1,
+            w[1].0,
+            w[1].1
+        ));
+    }
+
+    Ok(Stage {
+        events: Box::new(events.into_iter().map(Ok)),
+        speed_segments: segments,
+    })
+}
+
+/// Applies --speed-map segments, on the idle time limited timeline. The sped
+/// up segments are moved to final animation time.
+pub fn speed_map<'a>(stage: Stage<'a>, config: &Config) -> Stage<'a> {
+    let Stage {
+        events,
+        speed_segments: segments,
+    } = stage;
+
+    if segments.is_empty() {
+        return Stage::new(events);
+    }
+
+    info!("speed map: {segments:?}");
+
+    let map = speed::SpeedMap::new(segments);
+
+    let warped_segments = map
+        .warped_segments()
+        .into_iter()
+        .map(|(start, end, speed)| (start / config.speed, end / config.speed, speed))
+        .collect();
+
+    Stage {
+        events: Box::new(events::accelerate_map(events, map)),
+        speed_segments: warped_segments,
+    }
+}
+
+/// How long to freeze the frame at a marker: the number of seconds for
+/// markers labeled "hold:SECS", --marker-hold for others.
+pub fn marker_hold(label: &str, config: &Config) -> f64 {
+    label
+        .strip_prefix("hold:")
+        .and_then(|secs| secs.trim_end_matches('s').parse::<f64>().ok())
+        .filter(|secs| *secs >= 0.0)
+        .unwrap_or(config.marker_hold)
+}
+
+/// Moves sped up segments by the marker holds before them. A hold at the
+/// very start of a segment comes before it, one at its end comes after it.
+pub fn hold_segments(
+    segments: Vec<(f64, f64, f64)>,
+    markers: &[(f64, String)],
+    config: &Config,
+) -> Vec<(f64, f64, f64)> {
+    let mut holds = Vec::new();
+    let mut offset = 0.0;
+
+    // marker times already include the holds before them
+    for (time, label) in markers {
+        let hold = marker_hold(label, config);
+
+        if hold > 0.0 {
+            holds.push((time - offset, hold));
+            offset += hold;
+        }
+    }
+
+    let shift = |time: f64, inclusive: bool| -> f64 {
+        holds
+            .iter()
+            .filter(|(t, _)| *t < time || (inclusive && *t == time))
+            .map(|(_, hold)| hold)
+            .sum()
+    };
+
+    segments
+        .into_iter()
+        .map(|(start, end, speed)| (start + shift(start, true), end + shift(end, false), speed))
+        .collect()
+}
+
+/// Shortens long pauses, following the bounds of the speed segments along.
+pub fn idle_time<'a>(stage: Stage<'a>, itl: f64, config: &'a Config) -> Result<Stage<'a>> {
+    let Stage {
+        events,
+        speed_segments,
+    } = stage;
+
+    let compress = move |delay| config.idle_curve.compress(delay, itl, config.idle_scale);
+
+    if speed_segments.is_empty() {
+        let events: Box<dyn Iterator<Item = Result<Event>>> = match &config.idle_curve {
+            IdleCurve::Clip => Box::new(events::limit_idle_time(events, itl)),
+            _ => Box::new(events::compress_idle_time(events, compress)),
+        };
+
+        return Ok(Stage::new(events));
+    }
+
+    let events = events.collect::<Result<Vec<_>>>()?;
+
+    let bounds: Vec<f64> = speed_segments
+        .iter()
+        .flat_map(|(start, end, _)| [*start, *end])
+        .collect();
+
+    let bounds = events::compress_idle_time_points(&events, &bounds, compress);
+
+    let speed_segments = speed_segments
+        .iter()
+        .zip(bounds.chunks(2))
+        .map(|((_, _, speed), bounds)| (bounds[0], bounds[1], *speed))
+        .filter(|(start, end, _)| start < end)
+        .collect();
+
+    let events = events::compress_idle_time(events.into_iter().map(Ok), compress);
+
+    Ok(Stage {
+        events: Box::new(events),
+        speed_segments,
+    })
+}
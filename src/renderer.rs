@@ -1,16 +1,20 @@
 mod fontdue;
+mod resizing;
 mod resvg;
 
 use imgref::ImgVec;
 use rgb::{RGB8, RGBA8};
+use tiny_skia::Pixmap;
 
 use crate::theme::Theme;
 
 pub trait Renderer {
     fn render(&mut self, lines: &[avt::Line], cursor: Option<(usize, usize)>) -> ImgVec<RGBA8>;
     fn pixel_size(&self) -> (usize, usize);
+    fn set_title(&mut self, title: &str);
 }
 
+#[derive(Clone)]
 pub struct Settings {
     pub terminal_size: (usize, usize),
     pub font_db: fontdb::Database,
@@ -21,6 +25,80 @@ pub struct Settings {
     pub pixel_width: Option<usize>,
     pub pixel_height: Option<usize>,
     pub fill_background: bool,
+    /// Initial title, when a window title bar is drawn above the terminal
+    pub title_bar: Option<String>,
+}
+
+const TITLE_BAR_BUTTON_COLORS: [RGB8; 3] = [
+    RGB8::new(0xff, 0x5f, 0x57),
+    RGB8::new(0xfe, 0xbc, 0x2e),
+    RGB8::new(0x28, 0xc8, 0x40),
+];
+
+/// Geometry of the window title bar, shared by the renderers so that they
+/// lay it out the same way.
+pub(crate) struct TitleBar {
+    pub height: f64,
+    pub color: RGB8,
+    pub text_color: RGB8,
+    pub text_baseline: f64,
+    /// Center x, center y, radius and color of the window buttons
+    pub buttons: Vec<(f64, f64, f64, RGB8)>,
+    /// Horizontal space taken by the buttons, kept free on both sides of the
+    /// centered title
+    pub inset: f64,
+}
+
+impl TitleBar {
+    pub fn new(font_size: usize, line_height: f64, theme: &Theme) -> Self {
+        let font_size = font_size as f64;
+        let height = (font_size * line_height * 1.5).round();
+        let radius = height * 0.16;
+        let spacing = radius * 3.2;
+
+        let buttons = TITLE_BAR_BUTTON_COLORS
+            .iter()
+            .enumerate()
+            .map(|(i, color)| {
+                (
+                    height * 0.5 + i as f64 * spacing,
+                    height / 2.0,
+                    radius,
+                    *color,
+                )
+            })
+            .collect();
+
+        Self {
+            height,
+            color: mix(theme.foreground, theme.background, 0.1),
+            text_color: mix(theme.foreground, theme.background, 0.7),
+            text_baseline: (height / 2.0 + font_size * 0.35).round(),
+            buttons,
+            inset: height * 0.5 + 2.0 * spacing + radius * 2.0,
+        }
+    }
+
+    /// The title cut down to the number of characters which fit between the
+    /// buttons, with control characters left out.
+    pub fn fit(&self, title: &str, width: f64, char_width: f64) -> String {
+        let max_chars = ((width - 2.0 * self.inset) / char_width).max(0.0) as usize;
+        let chars: Vec<char> = title.chars().filter(|c| !c.is_control()).collect();
+
+        if chars.len() <= max_chars {
+            chars.into_iter().collect()
+        } else if max_chars == 0 {
+            String::new()
+        } else {
+            chars[..max_chars - 1].iter().chain(['…'].iter()).collect()
+        }
+    }
+}
+
+fn mix(fg: RGB8, bg: RGB8, ratio: f64) -> RGB8 {
+    let mix = |f: u8, b: u8| (f as f64 * ratio + b as f64 * (1.0 - ratio)).round() as u8;
+
+    RGB8::new(mix(fg.r, bg.r), mix(fg.g, bg.g), mix(fg.b, bg.b))
 }
 
 pub fn resvg<'a>(settings: Settings) -> resvg::ResvgRenderer<'a> {
@@ -31,16 +109,25 @@ pub fn fontdue(settings: Settings) -> fontdue::FontdueRenderer {
     fontdue::FontdueRenderer::new(settings)
 }
 
-struct TextAttrs {
-    foreground: Option<avt::Color>,
-    background: Option<avt::Color>,
-    bold: bool,
-    faint: bool,
-    italic: bool,
-    underline: bool,
+pub fn resizing(
+    settings: Settings,
+    max_terminal_size: (usize, usize),
+    policy: crate::ResizePolicy,
+    new_renderer: impl Fn(Settings) -> Box<dyn Renderer> + 'static,
+) -> resizing::ResizingRenderer {
+    resizing::ResizingRenderer::new(settings, max_terminal_size, policy, Box::new(new_renderer))
+}
+
+pub(crate) struct TextAttrs {
+    pub foreground: Option<avt::Color>,
+    pub background: Option<avt::Color>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
 }
 
-fn text_attrs(
+pub(crate) fn text_attrs(
     pen: &avt::Pen,
     cursor: &Option<(usize, usize)>,
     col: usize,
@@ -84,9 +171,71 @@ fn text_attrs(
     }
 }
 
-fn color_to_rgb(c: &avt::Color, theme: &Theme) -> RGB8 {
+pub(crate) fn color_to_rgb(c: &avt::Color, theme: &Theme) -> RGB8 {
     match c {
         avt::Color::RGB(c) => *c,
         avt::Color::Indexed(c) => theme.color(*c),
     }
 }
+
+/// Blends a premultiplied pixmap over an image at the given position.
+pub(crate) fn composite(
+    image: &mut ImgVec<RGBA8>,
+    pixmap: &Pixmap,
+    (x, y): (usize, usize),
+    alpha: f32,
+) {
+    let width = pixmap.width() as usize;
+
+    for (row, src) in pixmap.pixels().chunks(width).enumerate() {
+        let Some(dst) = image.rows_mut().nth(y + row) else {
+            break;
+        };
+
+        for (s, d) in src.iter().zip(dst.iter_mut().skip(x)) {
+            let sa = s.alpha() as f32 / 255.0 * alpha;
+
+            if sa == 0.0 {
+                continue;
+            }
+
+            let da = d.a as f32 / 255.0;
+            let out_a = sa + da * (1.0 - sa);
+
+            let blend = |s: u8, d: u8| -> u8 {
+                let s = s as f32 / 255.0 * alpha;
+                let d = d as f32 / 255.0 * da * (1.0 - sa);
+
+                ((s + d) / out_a * 255.0).round() as u8
+            };
+
+            *d = RGBA8::new(
+                blend(s.red(), d.r),
+                blend(s.green(), d.g),
+                blend(s.blue(), d.b),
+                (out_a * 255.0).round() as u8,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TitleBar;
+    use crate::theme::Theme;
+
+    #[test]
+    fn title_bar_fit() {
+        let theme: Theme = "000000,ffffff,000000,000000,000000,000000,000000,000000,000000,000000"
+            .parse()
+            .unwrap();
+
+        let title_bar = TitleBar::new(10, 1.0, &theme);
+        let width = 2.0 * title_bar.inset + 65.0;
+
+        assert_eq!(title_bar.fit("vim\tfoo", width, 10.0), "vimfoo");
+        assert_eq!(title_bar.fit("hello!", width, 10.0), "hello!");
+        assert_eq!(title_bar.fit("hello world", width, 10.0), "hello…");
+        assert_eq!(title_bar.fit("hello", 0.0, 10.0), "");
+    }
+}
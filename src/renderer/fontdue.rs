@@ -1,4 +1,4 @@
-use crate::renderer::{color_to_rgb, text_attrs, Renderer, Settings};
+use crate::renderer::{color_to_rgb, text_attrs, Renderer, Settings, TitleBar};
 use crate::theme::Theme;
 use imgref::ImgVec;
 use log::debug;
@@ -21,6 +21,8 @@ pub struct FontdueRenderer {
     font_db: fontdb::Database,
     glyph_cache: HashMap<CharVariant, Option<Glyph>>,
     font_cache: HashMap<FontFace, Option<fontdue::Font>>,
+    title_bar: Option<TitleBar>,
+    title: String,
 }
 
 fn get_font<T: AsRef<str> + std::fmt::Debug>(
@@ -80,6 +82,13 @@ impl FontdueRenderer {
             settings.theme.background.alpha(0)
         };
 
+        let title_bar = settings
+            .title_bar
+            .as_ref()
+            .map(|_| TitleBar::new(settings.font_size, settings.line_height, &settings.theme));
+
+        let title_bar_height = title_bar.as_ref().map(|t| t.height).unwrap_or(0.0);
+
         Self {
             font_db: settings.font_db,
             font_families: settings.font_families,
@@ -90,12 +99,14 @@ impl FontdueRenderer {
                 .unwrap_or(((cols + 2) as f64 * col_width).round() as usize),
             pixel_height: settings
                 .pixel_height
-                .unwrap_or(((rows + 1) as f64 * row_height).round() as usize),
+                .unwrap_or(((rows + 1) as f64 * row_height + title_bar_height).round() as usize),
             font_size: settings.font_size,
             col_width,
             row_height,
             font_cache: HashMap::new(),
             glyph_cache: HashMap::new(),
+            title_bar,
+            title: settings.title_bar.unwrap_or_default(),
         }
     }
 
@@ -164,6 +175,89 @@ impl FontdueRenderer {
                 None => None,
             })
     }
+
+    fn draw_glyph(
+        &self,
+        buf: &mut [RGBA8],
+        glyph: &Glyph,
+        (x, y): (i32, i32),
+        fg: RGBA8,
+        faint: bool,
+    ) {
+        let (metrics, bitmap) = glyph;
+
+        for bmap_y in 0..metrics.height {
+            let y = y + bmap_y as i32;
+
+            if y < 0 || y >= self.pixel_height as i32 {
+                continue;
+            }
+
+            for bmap_x in 0..metrics.width {
+                let x = x + bmap_x as i32;
+
+                if x < 0 || x >= self.pixel_width as i32 {
+                    continue;
+                }
+
+                let mut ratio = bitmap[bmap_y * metrics.width + bmap_x];
+
+                if faint {
+                    ratio = (ratio as f32 * 0.5) as u8;
+                }
+
+                let idx = (y as usize) * self.pixel_width + (x as usize);
+                let bg = buf[idx];
+
+                buf[idx] = mix_colors(fg, bg, ratio);
+            }
+        }
+    }
+
+    fn draw_title_bar(&mut self, buf: &mut [RGBA8]) {
+        let Some(title_bar) = &self.title_bar else {
+            return;
+        };
+
+        let height = (title_bar.height as usize).min(self.pixel_height);
+        let color = title_bar.color.alpha(255);
+        let text_color = title_bar.text_color.alpha(255);
+        let baseline = title_bar.text_baseline as i32;
+        let title = title_bar.fit(&self.title, self.pixel_width as f64, self.col_width);
+
+        buf[..height * self.pixel_width].fill(color);
+
+        for &(cx, cy, r, button_color) in &title_bar.buttons {
+            let button_color = button_color.alpha(255);
+            let (x_l, x_r) = ((cx - r - 1.0) as usize, (cx + r + 1.0) as usize);
+            let (y_t, y_b) = ((cy - r - 1.0) as usize, (cy + r + 1.0) as usize);
+
+            for y in y_t..=y_b.min(height - 1) {
+                for x in x_l..=x_r.min(self.pixel_width - 1) {
+                    let d = ((x as f64 + 0.5 - cx).powi(2) + (y as f64 + 0.5 - cy).powi(2)).sqrt();
+                    let coverage = (r + 0.5 - d).clamp(0.0, 1.0);
+                    let idx = y * self.pixel_width + x;
+
+                    buf[idx] = mix_colors(button_color, buf[idx], (coverage * 255.0) as u8);
+                }
+            }
+        }
+
+        let width = title.chars().count() as f64 * self.col_width;
+        let x_l = (self.pixel_width as f64 - width) / 2.0;
+
+        for (i, ch) in title.chars().enumerate() {
+            self.ensure_glyph(ch, false, false);
+
+            if let Some(glyph) = self.get_glyph(ch, false, false) {
+                let (metrics, _) = glyph;
+                let x = (x_l + i as f64 * self.col_width).round() as i32 + metrics.xmin;
+                let y = baseline - metrics.height as i32 - metrics.ymin;
+
+                self.draw_glyph(buf, glyph, (x, y), text_color, false);
+            }
+        }
+    }
 }
 
 fn mix_colors(fg: RGBA8, bg: RGBA8, ratio: u8) -> RGBA8 {
@@ -182,8 +276,15 @@ impl Renderer for FontdueRenderer {
         let mut buf: Vec<RGBA8> =
             vec![self.background_color; self.pixel_width * self.pixel_height];
 
+        self.draw_title_bar(&mut buf);
+
         let margin_l = self.col_width;
-        let margin_t = (self.row_height / 2.0).round() as usize;
+        let margin_t = self
+            .title_bar
+            .as_ref()
+            .map(|t| t.height as usize)
+            .unwrap_or(0)
+            + (self.row_height / 2.0).round() as usize;
 
         for (row, line) in lines.iter().enumerate() {
             let y_t = margin_t + (row as f64 * self.row_height).round() as usize;
@@ -237,42 +338,17 @@ impl Renderer for FontdueRenderer {
                     continue;
                 }
 
-                let (metrics, bitmap) = glyph.as_ref().unwrap();
+                let glyph = glyph.as_ref().unwrap();
+                let (metrics, _) = glyph;
 
                 let y_offset = (margin_t + self.font_size - metrics.height) as i32
                     + (row as f64 * self.row_height).round() as i32
                     - metrics.ymin;
 
-                for bmap_y in 0..metrics.height {
-                    let y = y_offset + bmap_y as i32;
-
-                    if y < 0 || y >= self.pixel_height as i32 {
-                        continue;
-                    }
-
-                    let x_offset = margin_l as i32
-                        + (col as f64 * self.col_width).round() as i32
-                        + metrics.xmin;
-
-                    for bmap_x in 0..metrics.width {
-                        let x = x_offset + bmap_x as i32;
-
-                        if x < 0 || x >= self.pixel_width as i32 {
-                            continue;
-                        }
-
-                        let mut ratio = bitmap[bmap_y * metrics.width + bmap_x];
-
-                        if attrs.faint {
-                            ratio = (ratio as f32 * 0.5) as u8;
-                        }
-
-                        let idx = (y as usize) * self.pixel_width + (x as usize);
-                        let bg = buf[idx];
+                let x_offset =
+                    margin_l as i32 + (col as f64 * self.col_width).round() as i32 + metrics.xmin;
 
-                        buf[idx] = mix_colors(fg, bg, ratio);
-                    }
-                }
+                self.draw_glyph(&mut buf, glyph, (x_offset, y_offset), fg, attrs.faint);
 
                 col += cell.width();
             }
@@ -284,4 +360,8 @@ impl Renderer for FontdueRenderer {
     fn pixel_size(&self) -> (usize, usize) {
         (self.pixel_width, self.pixel_height)
     }
+
+    fn set_title(&mut self, title: &str) {
+        title.clone_into(&mut self.title);
+    }
 }
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use imgref::{ImgRef, ImgVec};
+use rgb::{ComponentMap, RGBA8};
+
+use super::{Renderer, Settings};
+use crate::ResizePolicy;
+
+type NewRenderer = Box<dyn Fn(Settings) -> Box<dyn Renderer>>;
+
+/// Renders frames of a terminal that changes size over time onto a canvas
+/// of constant size, using a separate inner renderer for each grid size.
+pub struct ResizingRenderer {
+    settings: Settings,
+    new_renderer: NewRenderer,
+    renderers: HashMap<(usize, usize), Box<dyn Renderer>>,
+    policy: ResizePolicy,
+    canvas_size: (usize, usize),
+    background: RGBA8,
+}
+
+impl ResizingRenderer {
+    pub fn new(
+        settings: Settings,
+        max_terminal_size: (usize, usize),
+        policy: ResizePolicy,
+        new_renderer: NewRenderer,
+    ) -> Self {
+        let background = if settings.fill_background {
+            settings.theme.background.alpha(255)
+        } else {
+            settings.theme.background.alpha(0)
+        };
+
+        let canvas_terminal_size = match policy {
+            ResizePolicy::Max | ResizePolicy::Letterbox => max_terminal_size,
+            ResizePolicy::Scale => settings.terminal_size,
+        };
+
+        let mut renderer = Self {
+            settings,
+            new_renderer,
+            renderers: HashMap::new(),
+            policy,
+            canvas_size: (0, 0),
+            background,
+        };
+
+        renderer.canvas_size = renderer.renderer(canvas_terminal_size).pixel_size();
+
+        renderer
+    }
+
+    fn renderer(&mut self, terminal_size: (usize, usize)) -> &mut Box<dyn Renderer> {
+        let settings = &self.settings;
+        let new_renderer = &self.new_renderer;
+
+        self.renderers.entry(terminal_size).or_insert_with(|| {
+            new_renderer(Settings {
+                terminal_size,
+                ..settings.clone()
+            })
+        })
+    }
+}
+
+impl Renderer for ResizingRenderer {
+    fn render(&mut self, lines: &[avt::Line], cursor: Option<(usize, usize)>) -> ImgVec<RGBA8> {
+        let terminal_size = (lines.first().map(|l| l.len()).unwrap_or(0), lines.len());
+        let image = self.renderer(terminal_size).render(lines, cursor);
+        let (width, height) = self.canvas_size;
+
+        if (image.width(), image.height()) == (width, height) {
+            return image;
+        }
+
+        let image = match self.policy {
+            ResizePolicy::Max | ResizePolicy::Letterbox => image,
+
+            ResizePolicy::Scale => {
+                let ratio = (width as f64 / image.width() as f64)
+                    .min(height as f64 / image.height() as f64);
+
+                let scaled_width = ((image.width() as f64 * ratio).round() as usize).max(1);
+                let scaled_height = ((image.height() as f64 * ratio).round() as usize).max(1);
+
+                scale(image.as_ref(), scaled_width, scaled_height)
+            }
+        };
+
+        let (x, y) = match self.policy {
+            ResizePolicy::Max => (0, 0),
+
+            ResizePolicy::Letterbox | ResizePolicy::Scale => (
+                width.saturating_sub(image.width()) / 2,
+                height.saturating_sub(image.height()) / 2,
+            ),
+        };
+
+        let mut canvas = ImgVec::new(vec![self.background; width * height], width, height);
+
+        for (src, dst) in image.rows().zip(canvas.rows_mut().skip(y)) {
+            for (s, d) in src.iter().zip(dst.iter_mut().skip(x)) {
+                *d = *s;
+            }
+        }
+
+        canvas
+    }
+
+    fn pixel_size(&self) -> (usize, usize) {
+        self.canvas_size
+    }
+
+    fn set_title(&mut self, title: &str) {
+        if self.settings.title_bar.is_some() {
+            self.settings.title_bar = Some(title.to_owned());
+        }
+
+        for renderer in self.renderers.values_mut() {
+            renderer.set_title(title);
+        }
+    }
+}
+
+/// Resizes an image with bilinear filtering.
+fn scale(image: ImgRef<RGBA8>, width: usize, height: usize) -> ImgVec<RGBA8> {
+    let (src_width, src_height) = (image.width(), image.height());
+    let x_ratio = src_width as f32 / width as f32;
+    let y_ratio = src_height as f32 / height as f32;
+    let mut pixels = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        let sy = ((y as f32 + 0.5) * y_ratio - 0.5).clamp(0.0, (src_height - 1) as f32);
+        let (y0, fy) = (sy as usize, sy.fract());
+        let y1 = (y0 + 1).min(src_height - 1);
+
+        for x in 0..width {
+            let sx = ((x as f32 + 0.5) * x_ratio - 0.5).clamp(0.0, (src_width - 1) as f32);
+            let (x0, fx) = (sx as usize, sx.fract());
+            let x1 = (x0 + 1).min(src_width - 1);
+
+            let lerp = |a: RGBA8, b: RGBA8, t: f32| -> [f32; 4] {
+                let a = a.map(|c| c as f32);
+                let b = b.map(|c| c as f32);
+
+                [
+                    a.r + (b.r - a.r) * t,
+                    a.g + (b.g - a.g) * t,
+                    a.b + (b.b - a.b) * t,
+                    a.a + (b.a - a.a) * t,
+                ]
+            };
+
+            let top = lerp(image[(x0, y0)], image[(x1, y0)], fx);
+            let bottom = lerp(image[(x0, y1)], image[(x1, y1)], fx);
+            let c = |i: usize| (top[i] + (bottom[i] - top[i]) * fy).round() as u8;
+
+            pixels.push(RGBA8::new(c(0), c(1), c(2), c(3)));
+        }
+    }
+
+    ImgVec::new(pixels, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use imgref::ImgVec;
+    use rgb::RGBA8;
+
+    #[test]
+    fn scale() {
+        let black = RGBA8::new(0, 0, 0, 255);
+        let white = RGBA8::new(255, 255, 255, 255);
+        let image = ImgVec::new(vec![black, white, white, black], 2, 2);
+
+        let scaled = super::scale(image.as_ref(), 4, 4);
+
+        assert_eq!((scaled.width(), scaled.height()), (4, 4));
+        assert_eq!(scaled[(0usize, 0)], black);
+        assert_eq!(scaled[(3usize, 0)], white);
+        assert_eq!(scaled[(3usize, 3)], black);
+
+        let scaled = super::scale(image.as_ref(), 1, 1);
+
+        assert_eq!(scaled[(0usize, 0)].a, 255);
+        assert_eq!(scaled[(0usize, 0)].r, 128);
+    }
+}
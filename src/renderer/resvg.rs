@@ -1,8 +1,9 @@
-use super::{color_to_rgb, text_attrs, Renderer, Settings, TextAttrs};
+use super::{color_to_rgb, text_attrs, Renderer, Settings, TextAttrs, TitleBar};
 use crate::theme::Theme;
+use crate::vt::Frame;
 use imgref::ImgVec;
 use rgb::{FromSlice, RGBA8};
-use std::{fmt::Write, sync::Arc};
+use std::{collections::HashMap, fmt::Write, sync::Arc};
 use tiny_skia::Pixmap;
 
 pub struct ResvgRenderer<'a> {
@@ -15,6 +16,10 @@ pub struct ResvgRenderer<'a> {
     options: usvg::Options<'a>,
     transform: tiny_skia::Transform,
     header: String,
+    width: f64,
+    font_size: f64,
+    title_bar: Option<TitleBar>,
+    title: String,
 }
 
 fn color_to_style(color: &avt::Color, theme: &Theme) -> String {
@@ -67,6 +72,12 @@ impl<'a> ResvgRenderer<'a> {
         };
 
         let transform = tiny_skia::Transform::default();
+        let width = (settings.terminal_size.0 + 2) as f64 * (font_size * 0.6);
+
+        let title_bar = settings
+            .title_bar
+            .as_ref()
+            .map(|_| TitleBar::new(settings.font_size, settings.line_height, &settings.theme));
 
         let header = Self::header(
             settings.terminal_size,
@@ -75,27 +86,34 @@ impl<'a> ResvgRenderer<'a> {
             row_height,
             &settings.theme,
             settings.fill_background,
+            title_bar.as_ref(),
         );
 
-        let mut svg = header.clone();
-        svg.push_str(Self::footer());
-        let tree = usvg::Tree::from_str(&svg, &options).unwrap();
-        let pixel_width = settings.pixel_width.unwrap_or(tree.size().width() as usize);
-        let pixel_height = settings
-            .pixel_height
-            .unwrap_or(tree.size().height() as usize);
-
-        Self {
+        let mut renderer = Self {
             terminal_size: settings.terminal_size,
             theme: settings.theme,
-            pixel_width,
-            pixel_height,
+            pixel_width: 0,
+            pixel_height: 0,
             char_width,
             row_height,
             options,
             transform,
             header,
-        }
+            width,
+            font_size,
+            title_bar,
+            title: settings.title_bar.unwrap_or_default(),
+        };
+
+        let mut svg = renderer.header.clone();
+        svg.push_str(&renderer.footer());
+        let tree = usvg::Tree::from_str(&svg, &renderer.options).unwrap();
+        renderer.pixel_width = settings.pixel_width.unwrap_or(tree.size().width() as usize);
+        renderer.pixel_height = settings
+            .pixel_height
+            .unwrap_or(tree.size().height() as usize);
+
+        renderer
     }
 
     fn header(
@@ -105,9 +123,12 @@ impl<'a> ResvgRenderer<'a> {
         row_height: f64,
         theme: &Theme,
         fill_background: bool,
+        title_bar: Option<&TitleBar>,
     ) -> String {
         let width = (cols + 2) as f64 * (font_size * 0.6);
-        let height = (rows + 1) as f64 * row_height;
+        let terminal_height = (rows + 1) as f64 * row_height;
+        let title_bar_height = title_bar.map(|t| t.height).unwrap_or(0.0);
+        let height = terminal_height + title_bar_height;
         let x = 1.0 * 100.0 / (cols as f64 + 2.0);
         let y = 0.5 * 100.0 / (rows as f64 + 1.0);
 
@@ -130,6 +151,30 @@ impl<'a> ResvgRenderer<'a> {
             )
             .unwrap();
         }
+        if let Some(title_bar) = title_bar {
+            writeln!(
+                &mut header,
+                r#"<rect width="100%" height="{h}" rx="4" ry="4" style="fill: {c}" /><rect y="{}" width="100%" height="{}" style="fill: {c}" />"#,
+                title_bar.height / 2.0,
+                title_bar.height / 2.0,
+                h = title_bar.height,
+                c = title_bar.color
+            )
+            .unwrap();
+            for (cx, cy, r, color) in &title_bar.buttons {
+                writeln!(
+                    &mut header,
+                    r#"<circle cx="{cx:.1}" cy="{cy:.1}" r="{r:.1}" style="fill: {color}" />"#
+                )
+                .unwrap();
+            }
+            writeln!(
+                &mut header,
+                r#"<svg y="{}" width="{}" height="{}">"#,
+                title_bar.height, width, terminal_height
+            )
+            .unwrap();
+        }
         writeln!(
             &mut header,
             r#"<svg x="{:.3}%" y="{:.3}%" style="fill: {}">"#,
@@ -139,13 +184,27 @@ impl<'a> ResvgRenderer<'a> {
         header
     }
 
-    fn footer() -> &'static str {
-        "</svg></svg>"
+    fn footer(&self) -> String {
+        let Some(title_bar) = &self.title_bar else {
+            return "</svg></svg>".to_owned();
+        };
+
+        let title = title_bar.fit(&self.title, self.width, self.font_size * 0.6);
+
+        format!(
+            r#"</svg></svg><text x="50%" y="{}" text-anchor="middle" style="fill: {}">{}</text></svg>"#,
+            title_bar.text_baseline,
+            title_bar.text_color,
+            title
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        )
     }
 
     fn push_lines(&self, svg: &mut String, lines: &[avt::Line], cursor: Option<(usize, usize)>) {
-        self.push_background(svg, &lines, cursor);
-        self.push_text(svg, &lines, cursor);
+        self.push_background(svg, lines, cursor);
+        self.push_text(svg, lines, cursor);
     }
 
     fn push_background(
@@ -154,114 +213,222 @@ impl<'a> ResvgRenderer<'a> {
         lines: &[avt::Line],
         cursor: Option<(usize, usize)>,
     ) {
-        let (cols, rows) = self.terminal_size;
-
         svg.push_str(r#"<g style="shape-rendering: optimizeSpeed">"#);
 
         for (row, line) in lines.iter().enumerate() {
-            let y = 100.0 * (row as f64) / (rows as f64 + 1.0);
-            let mut col = 0;
-
-            for cell in line.cells() {
-                let attrs = text_attrs(cell.pen(), &cursor, col, row, &self.theme);
+            self.push_line_background(svg, line, row, self.row_y(row), cursor);
+        }
 
-                if attrs.background.is_none() {
-                    col += cell.width();
-                    continue;
-                }
+        svg.push_str("</g>");
+    }
 
-                let x = 100.0 * (col as f64) / (cols as f64 + 2.0);
-                let style = rect_style(&attrs, &self.theme);
-                let width = self.char_width * cell.width() as f64;
+    fn push_line_background(
+        &self,
+        svg: &mut String,
+        line: &avt::Line,
+        row: usize,
+        y: f64,
+        cursor: Option<(usize, usize)>,
+    ) {
+        let cols = self.terminal_size.0;
+        let mut col = 0;
 
-                let _ = write!(
-                    svg,
-                    r#"<rect x="{:.3}%" y="{:.3}%" width="{:.3}%" height="{:.3}" style="{}" />"#,
-                    x, y, width, self.row_height, style
-                );
+        for cell in line.cells() {
+            let attrs = text_attrs(cell.pen(), &cursor, col, row, &self.theme);
 
+            if attrs.background.is_none() {
                 col += cell.width();
+                continue;
             }
-        }
 
-        svg.push_str("</g>");
+            let x = 100.0 * (col as f64) / (cols as f64 + 2.0);
+            let style = rect_style(&attrs, &self.theme);
+            let width = self.char_width * cell.width() as f64;
+
+            let _ = write!(
+                svg,
+                r#"<rect x="{:.3}%" y="{:.3}%" width="{:.3}%" height="{:.3}" style="{}" />"#,
+                x, y, width, self.row_height, style
+            );
+
+            col += cell.width();
+        }
     }
 
     fn push_text(&self, svg: &mut String, lines: &[avt::Line], cursor: Option<(usize, usize)>) {
-        let (cols, rows) = self.terminal_size;
-
         svg.push_str(r#"<text class="default-text-fill">"#);
 
         for (row, line) in lines.iter().enumerate() {
-            let y = 100.0 * (row as f64) / (rows as f64 + 1.0);
-            let mut did_dy = false;
+            self.push_line_text(svg, line, row, self.row_y(row), cursor);
+        }
 
-            let _ = write!(svg, r#"<tspan y="{y:.3}%">"#);
-            let mut col = 0;
+        svg.push_str("</text>");
+    }
 
-            for cell in line.cells() {
-                let ch = cell.char();
+    fn push_line_text(
+        &self,
+        svg: &mut String,
+        line: &avt::Line,
+        row: usize,
+        y: f64,
+        cursor: Option<(usize, usize)>,
+    ) {
+        let cols = self.terminal_size.0;
+        let mut did_dy = false;
 
-                if ch == ' ' {
-                    col += cell.width();
-                    continue;
-                }
+        let _ = write!(svg, r#"<tspan y="{y:.3}%">"#);
+        let mut col = 0;
 
-                let attrs = text_attrs(cell.pen(), &cursor, col, row, &self.theme);
+        for cell in line.cells() {
+            let ch = cell.char();
 
-                svg.push_str("<tspan ");
+            if ch == ' ' {
+                col += cell.width();
+                continue;
+            }
 
-                if !did_dy {
-                    svg.push_str(r#"dy="1em" "#);
-                    did_dy = true;
-                }
+            let attrs = text_attrs(cell.pen(), &cursor, col, row, &self.theme);
 
-                let x = 100.0 * (col as f64) / (cols as f64 + 2.0);
-                let class = text_class(&attrs);
-                let style = text_style(&attrs, &self.theme);
+            svg.push_str("<tspan ");
 
-                let _ = write!(svg, r#"x="{x:.3}%" class="{class}" style="{style}">"#);
+            if !did_dy {
+                svg.push_str(r#"dy="1em" "#);
+                did_dy = true;
+            }
 
-                match ch {
-                    '\'' => {
-                        svg.push_str("&#39;");
-                    }
+            let x = 100.0 * (col as f64) / (cols as f64 + 2.0);
+            let class = text_class(&attrs);
+            let style = text_style(&attrs, &self.theme);
 
-                    '"' => {
-                        svg.push_str("&quot;");
-                    }
+            let _ = write!(svg, r#"x="{x:.3}%" class="{class}" style="{style}">"#);
 
-                    '&' => {
-                        svg.push_str("&amp;");
-                    }
+            match ch {
+                '\'' => {
+                    svg.push_str("&#39;");
+                }
 
-                    '>' => {
-                        svg.push_str("&gt;");
-                    }
+                '"' => {
+                    svg.push_str("&quot;");
+                }
 
-                    '<' => {
-                        svg.push_str("&lt;");
-                    }
+                '&' => {
+                    svg.push_str("&amp;");
+                }
 
-                    _ => {
-                        svg.push(ch);
-                    }
+                '>' => {
+                    svg.push_str("&gt;");
                 }
 
-                svg.push_str("</tspan>");
-                col += cell.width();
+                '<' => {
+                    svg.push_str("&lt;");
+                }
+
+                _ => {
+                    svg.push(ch);
+                }
             }
 
             svg.push_str("</tspan>");
+            col += cell.width();
         }
 
-        svg.push_str("</text>");
+        svg.push_str("</tspan>");
+    }
+
+    fn row_y(&self, row: usize) -> f64 {
+        100.0 * (row as f64) / (self.terminal_size.1 as f64 + 1.0)
     }
 
     pub fn render_svg(&self, lines: &[avt::Line], cursor: Option<(usize, usize)>) -> String {
         let mut svg = self.header.clone();
         self.push_lines(&mut svg, lines, cursor);
-        svg.push_str(Self::footer());
+        svg.push_str(&self.footer());
+        svg
+    }
+
+    /// Renders all frames into a single SVG document which plays them back
+    /// with CSS keyframes. Lines repeated across frames are defined only once.
+    pub fn render_animated_svg(&self, frames: &[Frame], duration: f64, repeat: bool) -> String {
+        let height = (self.terminal_size.1 + 1) as f64 * self.row_height;
+        let mut defs = String::new();
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        let mut screens = String::new();
+        let mut keyframes = String::new();
+
+        let mut define = |fragment: String, defs: &mut String| -> usize {
+            let next_id = ids.len();
+
+            *ids.entry(fragment).or_insert_with_key(|fragment| {
+                let _ = write!(defs, r#"<g id="l{next_id}">{fragment}</g>"#);
+                next_id
+            })
+        };
+
+        for (i, (time, lines, cursor)) in frames.iter().enumerate() {
+            let offset = i as f64 * height;
+            let progress = if i == 0 { 0.0 } else { 100.0 * time / duration };
+            let mut backgrounds = Vec::new();
+            let mut texts = Vec::new();
+
+            for (row, line) in lines.iter().enumerate() {
+                let mut background = String::new();
+                self.push_line_background(&mut background, line, row, 0.0, *cursor);
+
+                if !background.is_empty() {
+                    backgrounds.push((row, define(background, &mut defs)));
+                }
+
+                if line.cells().iter().any(|cell| cell.char() != ' ') {
+                    let mut text = r#"<text class="default-text-fill">"#.to_owned();
+                    self.push_line_text(&mut text, line, row, 0.0, *cursor);
+                    text.push_str("</text>");
+                    texts.push((row, define(text, &mut defs)));
+                }
+            }
+
+            let _ = write!(screens, r#"<g transform="translate(0 {offset:.3})">"#);
+            screens.push_str(r#"<g style="shape-rendering: optimizeSpeed">"#);
+
+            for (row, id) in backgrounds {
+                let y = self.row_y(row);
+                let _ = write!(screens, r##"<use xlink:href="#l{id}" y="{y:.3}%" />"##);
+            }
+
+            screens.push_str("</g>");
+
+            for (row, id) in texts {
+                let y = self.row_y(row);
+                let _ = write!(screens, r##"<use xlink:href="#l{id}" y="{y:.3}%" />"##);
+            }
+
+            screens.push_str("</g>");
+
+            let _ = writeln!(
+                keyframes,
+                "{progress:.3}% {{ transform: translateY(-{offset:.3}px) }}"
+            );
+        }
+
+        let last_offset = frames.len().saturating_sub(1) as f64 * height;
+        let _ = writeln!(
+            keyframes,
+            "100% {{ transform: translateY(-{last_offset:.3}px) }}"
+        );
+
+        let iterations = if repeat { "infinite" } else { "1" };
+        let mut svg = self.header.clone();
+
+        let _ = write!(
+            svg,
+            r#"<style>
+@keyframes play {{
+{keyframes}}}
+.screens {{ animation: play {duration:.3}s steps(1, end) {iterations} both }}
+</style>
+<defs>{defs}</defs><g class="screens">{screens}</g>"#
+        );
+
+        svg.push_str(&self.footer());
         svg
     }
 
@@ -288,4 +455,75 @@ impl<'a> Renderer for ResvgRenderer<'a> {
     fn pixel_size(&self) -> (usize, usize) {
         (self.pixel_width, self.pixel_height)
     }
+
+    fn set_title(&mut self, title: &str) {
+        title.clone_into(&mut self.title);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asciicast::Event;
+    use crate::renderer::Settings;
+    use crate::vt;
+
+    #[test]
+    fn render_animated_svg() {
+        let renderer = super::ResvgRenderer::new(Settings {
+            terminal_size: (10, 2),
+            font_db: fontdb::Database::new(),
+            font_families: vec!["monospace".to_owned()],
+            font_size: 14,
+            line_height: 1.4,
+            theme: "000000,ffffff,000000,ff0000,00ff00,ffff00,0000ff,ff00ff,00ffff,ffffff"
+                .parse()
+                .unwrap(),
+            pixel_width: None,
+            pixel_height: None,
+            fill_background: false,
+            title_bar: None,
+        });
+
+        let events = vec![
+            Event::Output(0.0, "hi".to_owned()),
+            Event::Output(1.0, "\r\n".to_owned()),
+            Event::Output(3.0, "\x1b[2J\x1b[Hhi".to_owned()),
+        ];
+
+        let frames = vt::frames(events.into_iter().map(Ok), (10, 2))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        let svg = renderer.render_animated_svg(&frames, 4.0, false);
+
+        // each screen is 3 rows of 19.6px high, including the margins
+        assert!(svg.contains(
+            "@keyframes play {
+0.000% { transform: translateY(-0.000px) }
+25.000% { transform: translateY(-58.800px) }
+75.000% { transform: translateY(-117.600px) }
+100% { transform: translateY(-117.600px) }
+}"
+        ));
+
+        assert!(svg.contains(".screens { animation: play 4.000s steps(1, end) 1 both }"));
+
+        let screens: Vec<&str> = svg.split(r#"<g transform="translate(0 "#).skip(1).collect();
+
+        assert_eq!(screens.len(), 3);
+        assert!(screens[2].starts_with("117.600)"));
+
+        // the last screen is the same as the first one, and reuses its lines
+        let lines = |screen: &str| -> Vec<String> {
+            screen
+                .split("<use ")
+                .skip(1)
+                .map(|line| line.split('"').nth(1).unwrap().to_owned())
+                .collect()
+        };
+
+        assert_eq!(lines(screens[0]), ["#l0", "#l1"]);
+        assert_eq!(lines(screens[2]), lines(screens[0]));
+        assert_ne!(lines(screens[1]), lines(screens[0]));
+    }
 }
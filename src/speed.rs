@@ -0,0 +1,110 @@
+use imgref::ImgVec;
+use rgb::RGBA8;
+
+use crate::badge::{self, Badge};
+
+/// Playback speed changing along the timeline: segments of (start, end,
+/// speed), sorted and not overlapping, with normal speed in between.
+pub struct SpeedMap(Vec<(f64, f64, f64)>);
+
+impl SpeedMap {
+    pub fn new(segments: Vec<(f64, f64, f64)>) -> Self {
+        Self(segments)
+    }
+
+    /// Maps a point in time onto the sped up timeline. The mapping is
+    /// continuous, so nothing jumps at segment boundaries.
+    pub fn warp(&self, time: f64) -> f64 {
+        let mut warped = 0.0;
+        let mut prev = 0.0;
+
+        for &(start, end, speed) in &self.0 {
+            if time <= start {
+                break;
+            }
+
+            warped += start - prev;
+            warped += (time.min(end) - start) / speed;
+            prev = end;
+
+            if time <= end {
+                return warped;
+            }
+        }
+
+        warped + (time - prev)
+    }
+
+    /// Segments played at other than normal speed, on the sped up timeline.
+    pub fn warped_segments(&self) -> Vec<(f64, f64, f64)> {
+        self.0
+            .iter()
+            .filter(|(_, _, speed)| *speed != 1.0)
+            .map(|&(start, end, speed)| (self.warp(start), self.warp(end), speed))
+            .collect()
+    }
+}
+
+/// Speed indicator, e.g. "8×", drawn in the top-left corner of frames within
+/// sped up segments.
+pub struct Indicator {
+    segments: Vec<(f64, f64, Option<Badge>)>,
+}
+
+impl Indicator {
+    pub fn new(segments: Vec<(f64, f64, f64)>, settings: badge::Settings) -> Self {
+        let segments = segments
+            .into_iter()
+            .map(|(start, end, speed)| {
+                let badge = Badge::label(&format!("{speed}×"), settings.clone());
+
+                (start, end, badge)
+            })
+            .collect();
+
+        Self { segments }
+    }
+
+    pub fn change_times(&self) -> Vec<f64> {
+        self.segments
+            .iter()
+            .flat_map(|(start, end, _)| [*start, *end])
+            .filter(|time| time.is_finite())
+            .collect()
+    }
+
+    pub fn apply(&self, image: &mut ImgVec<RGBA8>, time: f64) {
+        let badge = self
+            .segments
+            .iter()
+            .find(|(start, end, _)| *start <= time && time < *end)
+            .and_then(|(_, _, badge)| badge.as_ref());
+
+        if let Some(badge) = badge {
+            badge.apply_left(image);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpeedMap;
+
+    #[test]
+    fn warp() {
+        let map = SpeedMap::new(vec![(2.0, 6.0, 4.0), (8.0, f64::INFINITY, 0.5)]);
+
+        assert_eq!(map.warp(0.0), 0.0);
+        assert_eq!(map.warp(2.0), 2.0);
+        assert_eq!(map.warp(4.0), 2.5);
+        assert_eq!(map.warp(6.0), 3.0);
+        assert_eq!(map.warp(7.0), 4.0);
+        assert_eq!(map.warp(8.0), 5.0);
+        assert_eq!(map.warp(9.0), 7.0);
+
+        assert_eq!(
+            map.warped_segments(),
+            [(2.0, 3.0, 4.0), (5.0, f64::INFINITY, 0.5)]
+        );
+    }
+}
@@ -10,7 +10,7 @@ pub struct Theme {
     pub palette: Vec<RGB8>,
 }
 
-fn parse_hex_triplet(triplet: &str) -> anyhow::Result<RGB8> {
+pub(crate) fn parse_hex_triplet(triplet: &str) -> anyhow::Result<RGB8> {
     if triplet.len() < 6 || triplet.len() > 6 {
         bail!("{} is not a hex triplet", triplet);
     }
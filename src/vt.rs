@@ -1,10 +1,14 @@
+use std::{iter, mem};
+
 use anyhow::Result;
 use log::debug;
 
-type Frame = (f64, Vec<avt::Line>, Option<(usize, usize)>);
+use crate::asciicast::Event;
+
+pub type Frame = (f64, Vec<avt::Line>, Option<(usize, usize)>);
 
 pub fn frames(
-    stdout: impl Iterator<Item = Result<(f64, String)>>,
+    events: impl Iterator<Item = Result<Event>>,
     terminal_size: (usize, usize),
 ) -> impl Iterator<Item = Result<Frame>> {
     let mut vt = avt::Vt::builder()
@@ -14,20 +18,28 @@ pub fn frames(
 
     let mut prev_cursor = None;
 
-    stdout.filter_map(move |event| {
+    events.filter_map(move |event| {
         event
-            .map(|(time, data)| {
-                let changed_lines = vt.feed_str(&data).lines;
+            .map(|event| {
+                let prev_size = vt.size();
+
+                let (time, changed_lines) = match event {
+                    Event::Output(time, data) => (time, vt.feed_str(&data).lines),
+                    Event::Resize(_, cols, rows) if (cols, rows) == prev_size => return None,
+                    Event::Resize(time, cols, rows) => (time, vt.resize(cols, rows).lines),
+                    Event::Input(..) | Event::Marker(..) | Event::Exit(..) => return None,
+                };
+
                 let cursor: Option<(usize, usize)> = vt.cursor().into();
 
-                if !changed_lines.is_empty() || cursor != prev_cursor {
+                if !changed_lines.is_empty() || cursor != prev_cursor || vt.size() != prev_size {
                     prev_cursor = cursor;
                     let lines = vt.view().to_vec();
 
                     Some((time, lines, cursor))
                 } else {
                     prev_cursor = cursor;
-                    debug!("skipping frame with no visual changes: {:?}", data);
+                    debug!("skipping event with no visual changes at {}", time);
 
                     None
                 }
@@ -36,17 +48,119 @@ pub fn frames(
     })
 }
 
+/// Repeats the screen at extra points in time (sorted), so that anything
+/// drawn over frames can change while the terminal doesn't.
+pub fn repeat_at(
+    frames: impl Iterator<Item = Result<Frame>>,
+    times: Vec<f64>,
+) -> impl Iterator<Item = Result<Frame>> {
+    let mut frames = frames.peekable();
+    let mut times = times.into_iter().peekable();
+    let mut prev_frame: Option<Frame> = None;
+
+    iter::from_fn(move || {
+        if let Some((prev_time, lines, cursor)) = &prev_frame {
+            let next_time = match frames.peek() {
+                Some(Ok((time, ..))) => *time,
+                Some(Err(_)) => f64::NEG_INFINITY,
+                None => f64::INFINITY,
+            };
+
+            while times.next_if(|t| t <= prev_time).is_some() {}
+
+            if let Some(time) = times.next_if(|t| *t < next_time) {
+                return Some(Ok((time, lines.clone(), *cursor)));
+            }
+        }
+
+        let frame = frames.next()?;
+
+        if let Ok(frame) = &frame {
+            prev_frame = Some(frame.clone());
+        }
+
+        Some(frame)
+    })
+}
+
+/// Drops frames replaced by a later one at the same point in time, which
+/// would never be shown (e.g. a resize immediately followed by output).
+pub fn dedup(frames: impl Iterator<Item = Result<Frame>>) -> impl Iterator<Item = Result<Frame>> {
+    let mut frames = frames.peekable();
+
+    iter::from_fn(move || loop {
+        let frame = frames.next()?;
+
+        if let Ok((time, ..)) = &frame {
+            if matches!(frames.peek(), Some(Ok((next_time, ..))) if next_time == time) {
+                continue;
+            }
+        }
+
+        return Some(frame);
+    })
+}
+
+/// Window titles set by the program with OSC 0 and OSC 2 sequences, which
+/// the terminal emulator itself ignores.
+pub fn titles(events: &[Event]) -> Vec<(f64, String)> {
+    enum State {
+        Ground,
+        Escape,
+        Osc(String),
+        OscEscape(String),
+    }
+
+    let mut titles = Vec::new();
+    let mut state = State::Ground;
+
+    for event in events {
+        let Event::Output(time, data) = event else {
+            continue;
+        };
+
+        for ch in data.chars() {
+            state = match (mem::replace(&mut state, State::Ground), ch) {
+                (State::Osc(params), '\x1b') => State::OscEscape(params),
+                (_, '\x1b') => State::Escape,
+                (State::Escape | State::OscEscape(_), ']') => State::Osc(String::new()),
+
+                (State::Osc(params), '\x07') | (State::OscEscape(params), '\\') => {
+                    if let Some(("0" | "2", title)) = params.split_once(';') {
+                        titles.push((*time, title.to_owned()));
+                    }
+
+                    State::Ground
+                }
+
+                (State::Osc(_), '\x18' | '\x1a') => State::Ground,
+
+                (State::Osc(mut params), ch) => {
+                    params.push(ch);
+                    State::Osc(params)
+                }
+
+                _ => State::Ground,
+            };
+        }
+    }
+
+    titles
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
 
     #[test]
     fn frames() {
+        use crate::asciicast::Event::*;
+
         let stdout = [
-            (0.0, "foo".to_owned()),
-            (1.0, "\x1b[0m".to_owned()),
-            (2.0, "bar".to_owned()),
-            (3.0, "!".to_owned()),
+            Output(0.0, "foo".to_owned()),
+            Output(1.0, "\x1b[0m".to_owned()),
+            Output(2.0, "bar".to_owned()),
+            Output(3.0, "!".to_owned()),
         ];
 
         let fs = super::frames(stdout.into_iter().map(Ok), (4, 2))
@@ -79,4 +193,95 @@ mod tests {
         assert_eq!(lines[0], "foob");
         assert_eq!(lines[1], "ar! ");
     }
+
+    #[test]
+    fn frames_resize() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "foo".to_owned()),
+            Resize(1.0, 6, 3),
+            Resize(2.0, 6, 3),
+            Output(3.0, "bar".to_owned()),
+        ];
+
+        let fs = super::frames(events.into_iter().map(Ok), (4, 2))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(fs.len(), 3);
+
+        let (time, lines, _) = &fs[1];
+        let lines: Vec<String> = lines.iter().map(|l| l.text()).collect();
+
+        assert_eq!(*time, 1.0);
+        assert_eq!(lines, ["foo   ", "      ", "      "]);
+
+        let (time, lines, _) = &fs[2];
+        let lines: Vec<String> = lines.iter().map(|l| l.text()).collect();
+
+        assert_eq!(*time, 3.0);
+        assert_eq!(lines[0], "foobar");
+    }
+
+    #[test]
+    fn repeat_at() {
+        use crate::asciicast::Event::*;
+
+        let events = [Output(0.0, "foo".to_owned()), Output(2.0, "bar".to_owned())];
+        let frames = super::frames(events.into_iter().map(Ok), (8, 2));
+
+        let fs = super::repeat_at(frames, vec![0.0, 1.0, 1.5, 2.0, 3.0])
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        let times: Vec<f64> = fs.iter().map(|(time, ..)| *time).collect();
+
+        assert_eq!(times, [0.0, 1.0, 1.5, 2.0, 3.0]);
+        assert_eq!(fs[2].1[0].text(), "foo     ");
+        assert_eq!(fs[4].1[0].text(), "foobar  ");
+    }
+
+    #[test]
+    fn dedup() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "foo".to_owned()),
+            Resize(1.0, 6, 2),
+            Output(1.0, "bar".to_owned()),
+        ];
+
+        let frames = super::frames(events.into_iter().map(Ok), (8, 2));
+
+        let fs = super::dedup(frames).collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(fs.len(), 2);
+        assert_eq!(fs[1].0, 1.0);
+        assert_eq!(fs[1].1[0].text(), "foobar");
+    }
+
+    #[test]
+    fn titles() {
+        use crate::asciicast::Event::*;
+
+        let events = [
+            Output(0.0, "\x1b]0;first\x07foo".to_owned()),
+            Output(1.0, "\x1b]1;icon\x07\x1b]2;sec".to_owned()),
+            Output(2.0, "ond\x1b\\bar".to_owned()),
+            Marker(3.0, "\x1b]2;marker\x07".to_owned()),
+            Output(4.0, "\x1b]2;cancelled\x18\x1b]2;\x07".to_owned()),
+        ];
+
+        let titles = super::titles(&events);
+
+        assert_eq!(
+            titles,
+            [
+                (0.0, "first".to_owned()),
+                (2.0, "second".to_owned()),
+                (4.0, "".to_owned())
+            ]
+        );
+    }
 }